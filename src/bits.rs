@@ -0,0 +1,207 @@
+//! serializers working on bits, for bitstream formats
+//!
+//! Bit level serializers take and return a `BitContext`, which keeps the bits of the
+//! byte being filled. They are turned into regular serializers with `bits`.
+use crate::bytes::be_u8;
+use crate::internal::{GenError, SerializeFn, WriteContext};
+use crate::lib::std::io::Write;
+
+/// Context around a `WriteContext` that is passed through bit level serializing functions
+///
+/// Bits are written most significant bit first. Each byte is written to the underlying
+/// `WriteContext` once its 8 bits are known.
+pub struct BitContext<W> {
+    out: WriteContext<W>,
+    /// Bits of the pending byte, starting from its most significant bit
+    pending: u8,
+    /// Number of bits of `pending` that were written, always lower than 8
+    pending_len: u8,
+}
+
+impl<W: Write> BitContext<W> {
+    /// Returns the number of bits written since the start of serialization
+    pub fn bit_position(&self) -> u64 {
+        self.out.position * 8 + u64::from(self.pending_len)
+    }
+
+    fn push(mut self, value: u8, nbits: u8) -> BitResult<W> {
+        self.pending |= value << (8 - self.pending_len - nbits);
+        self.pending_len += nbits;
+
+        if self.pending_len == 8 {
+            self.out = be_u8(self.pending)(self.out)?;
+            self.pending = 0;
+            self.pending_len = 0;
+        }
+
+        Ok(self)
+    }
+}
+
+/// Types returned by bit level serializers
+pub type BitResult<W> = Result<BitContext<W>, GenError>;
+
+/// Trait for bit level serializing functions
+///
+/// Bit level serializers take a `BitContext` and return its updated version.
+pub trait BitSerializeFn<W>: Fn(BitContext<W>) -> BitResult<W> {}
+
+impl<W, F: Fn(BitContext<W>) -> BitResult<W>> BitSerializeFn<W> for F {}
+
+/// Applies the bit level serializer `f`, then fills the last byte with zero bits
+///
+/// The bits start on a byte boundary, and the output is always a whole number of bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, bits::{bits, bit_uint}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(bits(bit_uint(0b101, 3)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0b1010_0000][..]);
+/// ```
+pub fn bits<W: Write, F: BitSerializeFn<W>>(f: F) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let ctx = f(BitContext {
+            out,
+            pending: 0,
+            pending_len: 0,
+        })?;
+
+        if ctx.pending_len == 0 {
+            Ok(ctx.out)
+        } else {
+            be_u8(ctx.pending)(ctx.out)
+        }
+    }
+}
+
+/// Writes the `nbits` lowest bits of `value`, most significant bit first
+///
+/// `nbits` can be anything from 1 to 64, and the bits do not have to be aligned on bytes.
+/// If `nbits` is out of that range or `value` does not fit in `nbits` bits, this fails
+/// with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bits::{bits, bit_uint}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     bits(|b| bit_uint(0x1, 4)(b).and_then(bit_uint(0x234, 12))),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x12, 0x34][..]);
+/// ```
+pub fn bit_uint<W: Write>(value: u64, nbits: usize) -> impl BitSerializeFn<W> {
+    move |mut ctx: BitContext<W>| {
+        if nbits == 0 || nbits > 64 || (nbits < 64 && value >> nbits != 0) {
+            return Err(GenError::InvalidValue);
+        }
+
+        let mut remaining = nbits;
+        while remaining > 0 {
+            let n = crate::lib::std::cmp::min(usize::from(8 - ctx.pending_len), remaining);
+            let chunk = (value >> (remaining - n)) & ((1 << n) - 1);
+            ctx = ctx.push(chunk as u8, n as u8)?;
+            remaining -= n;
+        }
+
+        Ok(ctx)
+    }
+}
+
+/// Writes a single bit, set if `value` is true
+///
+/// ```rust
+/// use cookie_factory::{gen, bits::{bits, bit_bool, bit_uint}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     bits(|b| bit_bool(true)(b).and_then(bit_uint(0x7, 3))),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0b1111_0000][..]);
+/// ```
+pub fn bit_bool<W: Write>(value: bool) -> impl BitSerializeFn<W> {
+    move |ctx: BitContext<W>| ctx.push(value as u8, 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::gen;
+
+    #[test]
+    fn test_bit_uint() {
+        let mut buf = [0u8; 5];
+        let serializer = bits(|b| {
+            bit_uint(0xA_BCDE, 20)(b)
+                .and_then(bit_uint(0x123, 12))
+                .and_then(bit_uint(0b1, 1))
+        });
+        let (out, pos) = gen(serializer, &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert!(out.is_empty());
+        assert_eq!(buf, [0xAB, 0xCD, 0xE1, 0x23, 0x80]);
+
+        let mut buf = [0u8; 9];
+        let serializer = bits(|b| bit_uint(0, 4)(b).and_then(bit_uint(u64::MAX, 64)));
+        gen(serializer, &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x0F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xF0]);
+    }
+
+    #[test]
+    fn test_bit_uint_invalid() {
+        let mut buf = [0u8; 8];
+
+        for &(value, nbits) in &[(0x10, 4), (0, 0), (0, 65)] {
+            let res = gen(bits(bit_uint(value, nbits)), &mut buf[..]);
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+        }
+
+        let res = gen(bits(bit_uint(0x1_2345, 17)), &mut buf[..2]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_bit_bool() {
+        let mut buf = [0u8; 2];
+        let serializer = bits(|b| {
+            bit_bool(true)(b)
+                .and_then(bit_bool(false))
+                .and_then(bit_bool(true))
+                .and_then(bit_bool(true))
+                .and_then(bit_uint(0b0110, 4))
+        });
+        let (out, pos) = gen(serializer, &mut buf[..]).unwrap();
+        assert_eq!(pos, 1);
+        assert_eq!(out.len(), 1);
+        assert_eq!(buf, [0b1011_0110, 0]);
+
+        // the bit following a full byte starts a new one, padded with zeros
+        let serializer = bits(|b| {
+            bit_uint(0x7f, 7)(b)
+                .and_then(bit_bool(false))
+                .and_then(bit_bool(true))
+        });
+        gen(serializer, &mut buf[..]).unwrap();
+        assert_eq!(buf, [0b1111_1110, 0b1000_0000]);
+    }
+}
@@ -1,5 +1,5 @@
 //! bytes and numbers related serialization functions
-use crate::internal::{SerializeFn, WriteContext, GenError};
+use crate::internal::{GenError, GenResult, MaxSize, SerializeFn, WriteContext};
 use crate::lib::std::io::Write;
 
 macro_rules! try_write(($out:ident, $len:ident, $data:expr) => (
@@ -115,6 +115,27 @@ pub fn be_u64<W: Write>(i: u64) -> impl SerializeFn<W> {
     move |mut out: WriteContext<W>| try_write!(out, len, &i.to_be_bytes()[..])
 }
 
+/// Writes an `u128` in big endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u128};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u128(1u128), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 16);
+///   assert_eq!(buf.len(), 100 - 16);
+/// }
+///
+/// assert_eq!(&buf[..16], &[0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 1u8][..]);
+/// ```
+pub fn be_u128<W: Write>(i: u128) -> impl SerializeFn<W> {
+    let len = 16;
+
+    move |mut out: WriteContext<W>| try_write!(out, len, &i.to_be_bytes()[..])
+}
+
 /// Writes an `i8` to the output
 ///
 /// ```rust
@@ -210,6 +231,25 @@ pub fn be_i64<W: Write>(i: i64) -> impl SerializeFn<W> {
     be_u64(i as u64)
 }
 
+/// Writes an `i128` in big endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_i128};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_i128(1i128), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 16);
+///   assert_eq!(buf.len(), 100 - 16);
+/// }
+///
+/// assert_eq!(&buf[..16], &[0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 1u8][..]);
+/// ```
+pub fn be_i128<W: Write>(i: i128) -> impl SerializeFn<W> {
+    be_u128(i as u128)
+}
+
 /// Writes an `f32` in big endian byte order to the output
 ///
 /// ```rust
@@ -248,6 +288,101 @@ pub fn be_f64<W: Write>(i: f64) -> impl SerializeFn<W> {
     be_u64(i.to_bits())
 }
 
+/// Writes the bit pattern of an `f32` in big endian byte order to the output
+///
+/// This writes the same bytes as `be_f32`, and is meant for format code where the value
+/// is handled as raw bits, see `u32_as_f32_be` for the inverse.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::f32_bits_be};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(f32_bits_be(1.0), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x3f, 0x80, 0x00, 0x00][..]);
+/// ```
+pub fn f32_bits_be<W: Write>(i: f32) -> impl SerializeFn<W> {
+    be_u32(i.to_bits())
+}
+
+/// Writes the `f32` with the bit pattern `bits` in big endian byte order to the output
+///
+/// This is the same as `be_f32(f32::from_bits(bits))`, without the conversion at the call
+/// site.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::u32_as_f32_be};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(u32_as_f32_be(0x3f80_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x3f, 0x80, 0x00, 0x00][..]);
+/// ```
+pub fn u32_as_f32_be<W: Write>(bits: u32) -> impl SerializeFn<W> {
+    be_f32(f32::from_bits(bits))
+}
+
+/// Writes an `f64` as an 80 bit IEEE 754 extended precision float in big endian byte
+/// order to the output
+///
+/// This is the format of the sample rate in AIFF files. It uses a 15 bit exponent and a
+/// 64 bit mantissa with an explicit integer bit, so every `f64` is represented exactly,
+/// including subnormals, infinities and NaNs.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_f80};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_f80(1.0), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 10);
+///   assert_eq!(buf.len(), 100 - 10);
+/// }
+///
+/// assert_eq!(&buf[..10], &[0x3f, 0xff, 0x80, 0, 0, 0, 0, 0, 0, 0][..]);
+/// ```
+pub fn be_f80<W: Write>(i: f64) -> impl SerializeFn<W> {
+    let (sign_exponent, mantissa) = f80_parts(i);
+
+    move |out: WriteContext<W>| be_u64(mantissa)(be_u16(sign_exponent)(out)?)
+}
+
+// Returns the sign and exponent, then the mantissa of the 80 bit extended precision
+// representation of `f`
+fn f80_parts(f: f64) -> (u16, u64) {
+    let bits = f.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exponent = ((bits >> 52) & 0x7ff) as u16;
+    let fraction = bits & 0x000f_ffff_ffff_ffff;
+
+    match exponent {
+        0 if fraction == 0 => (sign, 0),
+        // subnormal: normalize so that the integer bit is set
+        0 => {
+            let shift = fraction.leading_zeros();
+            let exponent = 16383 - 1022 - (shift - 11) as u16;
+            (sign | exponent, fraction << shift)
+        }
+        // infinities and NaNs, keeping the NaN payload
+        0x7ff => (sign | 0x7fff, (1 << 63) | (fraction << 11)),
+        _ => (
+            sign | (exponent + 16383 - 1023),
+            (1 << 63) | (fraction << 11),
+        ),
+    }
+}
+
 /// Writes an `u8` to the output
 ///
 /// ```rust
@@ -353,6 +488,27 @@ pub fn le_u64<W: Write>(i: u64) -> impl SerializeFn<W> {
     move |mut out: WriteContext<W>| try_write!(out, len, &i.to_le_bytes()[..])
 }
 
+/// Writes an `u128` in little endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u128};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u128(1u128), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 16);
+///   assert_eq!(buf.len(), 100 - 16);
+/// }
+///
+/// assert_eq!(&buf[..16], &[1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn le_u128<W: Write>(i: u128) -> impl SerializeFn<W> {
+    let len = 16;
+
+    move |mut out: WriteContext<W>| try_write!(out, len, &i.to_le_bytes()[..])
+}
+
 /// Writes an `i8` to the output
 ///
 /// ```rust
@@ -448,6 +604,25 @@ pub fn le_i64<W: Write>(i: i64) -> impl SerializeFn<W> {
     le_u64(i as u64)
 }
 
+/// Writes an `i128` in little endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_i128};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_i128(1i128), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 16);
+///   assert_eq!(buf.len(), 100 - 16);
+/// }
+///
+/// assert_eq!(&buf[..16], &[1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn le_i128<W: Write>(i: i128) -> impl SerializeFn<W> {
+    le_u128(i as u128)
+}
+
 /// Writes an `f32` in little endian byte order to the output
 ///
 /// ```rust
@@ -522,7 +697,7 @@ pub fn ne_u8<W: Write>(i: u8) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..2], &[0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..2], &[1u8, 0u8][..]);
 /// ```
 pub fn ne_u16<W: Write>(i: u16) -> impl SerializeFn<W> {
@@ -546,13 +721,20 @@ pub fn ne_u16<W: Write>(i: u16) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..3], &[0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..3], &[1u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_u24<W: Write>(i: u32) -> impl SerializeFn<W> {
     let len = 3;
 
-    move |mut out: WriteContext<W>| try_write!(out, len, &i.to_ne_bytes()[1..])
+    move |mut out: WriteContext<W>| {
+        #[cfg(target_endian = "big")]
+        let bytes = &i.to_ne_bytes()[1..];
+        #[cfg(target_endian = "little")]
+        let bytes = &i.to_ne_bytes()[..3];
+
+        try_write!(out, len, bytes)
+    }
 }
 
 /// Writes an `u32` in native byte order to the output
@@ -570,7 +752,7 @@ pub fn ne_u24<W: Write>(i: u32) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..4], &[0u8, 0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..4], &[1u8, 0u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_u32<W: Write>(i: u32) -> impl SerializeFn<W> {
@@ -594,7 +776,7 @@ pub fn ne_u32<W: Write>(i: u32) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..8], &[0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..8], &[1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_u64<W: Write>(i: u64) -> impl SerializeFn<W> {
@@ -637,7 +819,7 @@ pub fn ne_i8<W: Write>(i: i8) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..2], &[0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..2], &[1u8, 0u8][..]);
 /// ```
 pub fn ne_i16<W: Write>(i: i16) -> impl SerializeFn<W> {
@@ -659,7 +841,7 @@ pub fn ne_i16<W: Write>(i: i16) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..3], &[0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..3], &[1u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_i24<W: Write>(i: i32) -> impl SerializeFn<W> {
@@ -681,7 +863,7 @@ pub fn ne_i24<W: Write>(i: i32) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..4], &[0u8, 0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..4], &[1u8, 0u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_i32<W: Write>(i: i32) -> impl SerializeFn<W> {
@@ -703,7 +885,7 @@ pub fn ne_i32<W: Write>(i: i32) -> impl SerializeFn<W> {
 ///
 /// #[cfg(target_endian = "big")]
 /// assert_eq!(&buf[..8], &[0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 1u8][..]);
-/// #[cfg(target_endian = "litte")]
+/// #[cfg(target_endian = "little")]
 /// assert_eq!(&buf[..8], &[1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..]);
 /// ```
 pub fn ne_i64<W: Write>(i: i64) -> impl SerializeFn<W> {
@@ -753,3 +935,2110 @@ pub fn ne_f32<W: Write>(i: f32) -> impl SerializeFn<W> {
 pub fn ne_f64<W: Write>(i: f64) -> impl SerializeFn<W> {
     ne_u64(i.to_bits())
 }
+
+/// Byte order of the numbers written by the runtime-selected serializers like `endian_u32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// Big endian byte order
+    Big,
+    /// Little endian byte order
+    Little,
+    /// Byte order of the target platform
+    Native,
+}
+
+// Generates functions writing a number in the byte order given at runtime, dispatching
+// to the matching `be_`, `le_` or `ne_` function.
+macro_rules! endianness_impl(
+  ($($name:ident: $ty:ty => $be:ident, $le:ident, $ne:ident, $desc:expr);+ $(;)?) => (
+    $(
+      #[doc = "Writes "]
+      #[doc = $desc]
+      #[doc = " in the byte order given by `endian`"]
+      pub fn $name<W: Write>(endian: Endianness, i: $ty) -> impl SerializeFn<W> {
+        move |out: WriteContext<W>| match endian {
+          Endianness::Big => $be(i)(out),
+          Endianness::Little => $le(i)(out),
+          Endianness::Native => $ne(i)(out),
+        }
+      }
+    )+
+  );
+);
+
+endianness_impl!(
+    endian_u16: u16 => be_u16, le_u16, ne_u16, "an `u16`";
+    endian_u24: u32 => be_u24, le_u24, ne_u24, "the lower 24 bit of an `u32`";
+    endian_u32: u32 => be_u32, le_u32, ne_u32, "an `u32`";
+    endian_u64: u64 => be_u64, le_u64, ne_u64, "an `u64`";
+    endian_i16: i16 => be_i16, le_i16, ne_i16, "an `i16`";
+    endian_i24: i32 => be_i24, le_i24, ne_i24, "the lower 24 bit of an `i32`";
+    endian_i32: i32 => be_i32, le_i32, ne_i32, "an `i32`";
+    endian_i64: i64 => be_i64, le_i64, ne_i64, "an `i64`";
+    endian_f32: f32 => be_f32, le_f32, ne_f32, "an `f32`";
+    endian_f64: f64 => be_f64, le_f64, ne_f64, "an `f64`";
+);
+
+// Generates functions writing a slice of floats as consecutive IEEE 754 values, with a single
+// length for the whole run when reporting a `BufferTooSmall` error. The values are encoded
+// in a stack buffer of `FLOAT_ARRAY_CHUNK` bytes, which is written at once.
+macro_rules! float_array_impl(
+  ($($name:ident: $ty:ty => $to_bytes:ident, $desc:expr);+ $(;)?) => (
+    $(
+      #[doc = "Writes a slice of `"]
+      #[doc = stringify!($ty)]
+      #[doc = "` in "]
+      #[doc = $desc]
+      #[doc = " byte order to the output"]
+      pub fn $name<S: AsRef<[$ty]>, W: Write>(data: S) -> impl SerializeFn<W> {
+        move |mut out: WriteContext<W>| {
+          const SIZE: usize = crate::lib::std::mem::size_of::<$ty>();
+
+          let data = data.as_ref();
+          let len = data.len() * SIZE;
+          let mut written = 0;
+          let mut bytes = [0u8; FLOAT_ARRAY_CHUNK];
+
+          for values in data.chunks(FLOAT_ARRAY_CHUNK / SIZE) {
+            let chunk = &mut bytes[..values.len() * SIZE];
+            for (dst, value) in chunk.chunks_mut(SIZE).zip(values) {
+              dst.copy_from_slice(&value.to_bits().$to_bytes());
+            }
+
+            match out.write(chunk) {
+              Err(io) => return Err(GenError::IoError(io)),
+              Ok(n) if n < chunk.len() => return Err(GenError::BufferTooSmall(len - written - n)),
+              Ok(n) => written += n,
+            }
+          }
+
+          Ok(out)
+        }
+      }
+    )+
+  );
+);
+
+const FLOAT_ARRAY_CHUNK: usize = 256;
+
+float_array_impl!(
+    be_f32_array: f32 => to_be_bytes, "big endian";
+    le_f32_array: f32 => to_le_bytes, "little endian";
+    be_f64_array: f64 => to_be_bytes, "big endian";
+    le_f64_array: f64 => to_le_bytes, "little endian";
+);
+
+/// Writes an `u32` as an `u16` in big endian byte order, clamping it to `0xffff`
+///
+/// This is lossy: values larger than `0xffff` are written as `0xffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u16_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u16_saturating(0x1_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0xffu8, 0xffu8][..]);
+/// ```
+pub fn be_u16_saturating<W: Write>(i: u32) -> impl SerializeFn<W> {
+    be_u16(crate::lib::std::cmp::min(i, 0xffff) as u16)
+}
+
+/// Writes an `u32` as a 24 bit unsigned integer in big endian byte order, clamping it to `0xff_ffff`
+///
+/// This is lossy: values larger than `0xff_ffff` are written as `0xff_ffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u24_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u24_saturating(0x100_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0xffu8, 0xffu8, 0xffu8][..]);
+/// ```
+pub fn be_u24_saturating<W: Write>(i: u32) -> impl SerializeFn<W> {
+    be_u24(crate::lib::std::cmp::min(i, 0xff_ffff))
+}
+
+/// Writes an `u64` as an `u32` in big endian byte order, clamping it to `0xffff_ffff`
+///
+/// This is lossy: values larger than `0xffff_ffff` are written as `0xffff_ffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u32_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u32_saturating(0x1_0000_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0xffu8, 0xffu8, 0xffu8, 0xffu8][..]);
+/// ```
+pub fn be_u32_saturating<W: Write>(i: u64) -> impl SerializeFn<W> {
+    be_u32(crate::lib::std::cmp::min(i, 0xffff_ffff) as u32)
+}
+
+/// Writes an `u32` as an `u16` in little endian byte order, clamping it to `0xffff`
+///
+/// This is lossy: values larger than `0xffff` are written as `0xffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u16_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u16_saturating(0x1_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0xffu8, 0xffu8][..]);
+/// ```
+pub fn le_u16_saturating<W: Write>(i: u32) -> impl SerializeFn<W> {
+    le_u16(crate::lib::std::cmp::min(i, 0xffff) as u16)
+}
+
+/// Writes an `u32` as a 24 bit unsigned integer in little endian byte order, clamping it to `0xff_ffff`
+///
+/// This is lossy: values larger than `0xff_ffff` are written as `0xff_ffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u24_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u24_saturating(0x100_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0xffu8, 0xffu8, 0xffu8][..]);
+/// ```
+pub fn le_u24_saturating<W: Write>(i: u32) -> impl SerializeFn<W> {
+    le_u24(crate::lib::std::cmp::min(i, 0xff_ffff))
+}
+
+/// Writes an `u64` as an `u32` in little endian byte order, clamping it to `0xffff_ffff`
+///
+/// This is lossy: values larger than `0xffff_ffff` are written as `0xffff_ffff` instead of failing.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u32_saturating};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u32_saturating(0x1_0000_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0xffu8, 0xffu8, 0xffu8, 0xffu8][..]);
+/// ```
+pub fn le_u32_saturating<W: Write>(i: u64) -> impl SerializeFn<W> {
+    le_u32(crate::lib::std::cmp::min(i, 0xffff_ffff) as u32)
+}
+
+/// Writes the `u64` made of the `hi` and `lo` 32 bit halves in big endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u64_split};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u64_split(0x01020304, 0x05060708), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8][..]);
+/// ```
+pub fn be_u64_split<W: Write>(hi: u32, lo: u32) -> impl SerializeFn<W> {
+    be_u64(u64::from(hi) << 32 | u64::from(lo))
+}
+
+/// Writes the `u64` made of the `hi` and `lo` 32 bit halves in little endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u64_split};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u64_split(0x01020304, 0x05060708), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[8u8, 7u8, 6u8, 5u8, 4u8, 3u8, 2u8, 1u8][..]);
+/// ```
+pub fn le_u64_split<W: Write>(hi: u32, lo: u32) -> impl SerializeFn<W> {
+    le_u64(u64::from(hi) << 32 | u64::from(lo))
+}
+
+/// Writes the `lo` then the `hi` 32 bit halves of an `u64`, each in big endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u64_split_swapped};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u64_split_swapped(0x01020304, 0x05060708), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[5u8, 6u8, 7u8, 8u8, 1u8, 2u8, 3u8, 4u8][..]);
+/// ```
+pub fn be_u64_split_swapped<W: Write>(hi: u32, lo: u32) -> impl SerializeFn<W> {
+    be_u64(u64::from(lo) << 32 | u64::from(hi))
+}
+
+/// Writes the `hi` then the `lo` 32 bit halves of an `u64`, each in little endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_u64_split_swapped};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_u64_split_swapped(0x01020304, 0x05060708), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[4u8, 3u8, 2u8, 1u8, 8u8, 7u8, 6u8, 5u8][..]);
+/// ```
+pub fn le_u64_split_swapped<W: Write>(hi: u32, lo: u32) -> impl SerializeFn<W> {
+    le_u64(u64::from(lo) << 32 | u64::from(hi))
+}
+
+/// Writes an unsigned rational as its numerator then its denominator, each as a `u32` in
+/// big endian byte order
+///
+/// This is the TIFF and EXIF `RATIONAL` type. If `den` is 0, this fails with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_rational};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_rational(1, 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0u8, 0u8, 0u8, 1u8, 0u8, 0u8, 0u8, 3u8][..]);
+/// ```
+pub fn be_rational<W: Write>(num: u32, den: u32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if den == 0 {
+            return Err(GenError::InvalidValue);
+        }
+        be_u64_split(num, den)(out)
+    }
+}
+
+/// Writes an unsigned rational as its numerator then its denominator, each as a `u32` in
+/// little endian byte order
+///
+/// This is the TIFF and EXIF `RATIONAL` type. If `den` is 0, this fails with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_rational};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_rational(1, 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[1u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn le_rational<W: Write>(num: u32, den: u32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if den == 0 {
+            return Err(GenError::InvalidValue);
+        }
+        le_u64_split_swapped(num, den)(out)
+    }
+}
+
+/// Writes a signed rational as its numerator then its denominator, each as an `i32` in
+/// big endian byte order
+///
+/// This is the TIFF and EXIF `SRATIONAL` type. If `den` is 0, this fails with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_srational};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_srational(-1, 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0xffu8, 0xffu8, 0xffu8, 0xffu8, 0u8, 0u8, 0u8, 3u8][..]);
+/// ```
+pub fn be_srational<W: Write>(num: i32, den: i32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if den == 0 {
+            return Err(GenError::InvalidValue);
+        }
+        be_u64_split(num as u32, den as u32)(out)
+    }
+}
+
+/// Writes a signed rational as its numerator then its denominator, each as an `i32` in
+/// little endian byte order
+///
+/// This is the TIFF and EXIF `SRATIONAL` type. If `den` is 0, this fails with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le_srational};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le_srational(-1, 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0xffu8, 0xffu8, 0xffu8, 0xffu8, 3u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn le_srational<W: Write>(num: i32, den: i32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if den == 0 {
+            return Err(GenError::InvalidValue);
+        }
+        le_u64_split_swapped(num as u32, den as u32)(out)
+    }
+}
+
+/// Writes an unsigned number as packed BCD on `nbytes` bytes, two decimal digits per byte
+///
+/// The most significant digit comes first, in the high nibble of the first byte, and the
+/// number is padded with leading zeros. If it has more than `nbytes * 2` digits, this fails
+/// with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::bcd};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(bcd(1234, 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0x00u8, 0x12u8, 0x34u8][..]);
+/// ```
+pub fn bcd<W: Write>(value: u64, nbytes: usize) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        bcd_write(out, value, nbytes, |pair| ((pair / 10) << 4) | (pair % 10))
+    }
+}
+
+/// Writes an unsigned number as packed BCD on `nbytes` bytes, with swapped nibbles
+///
+/// This works like `bcd`, except that the first digit of each byte is stored in its low
+/// nibble, as used by some telecom formats.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::bcd_swapped};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(bcd_swapped(1234, 2), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x21u8, 0x43u8][..]);
+/// ```
+pub fn bcd_swapped<W: Write>(value: u64, nbytes: usize) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        bcd_write(out, value, nbytes, |pair| ((pair % 10) << 4) | (pair / 10))
+    }
+}
+
+// Writes `value` as `nbytes` packed BCD bytes, most significant first, `pack` turning each
+// pair of digits (0 to 99) into a byte.
+fn bcd_write<W: Write>(
+    mut out: WriteContext<W>,
+    value: u64,
+    nbytes: usize,
+    pack: impl Fn(u8) -> u8,
+) -> GenResult<W> {
+    // an u64 has at most 20 digits, so 10 bytes or more can hold any value
+    if nbytes < 10 && value >= 100u64.pow(nbytes as u32) {
+        return Err(GenError::InvalidValue);
+    }
+
+    for index in (0..nbytes).rev() {
+        let pair = if index < 10 {
+            value / 100u64.pow(index as u32) % 100
+        } else {
+            0
+        };
+        let len = 1;
+        out = try_write!(out, len, &[pack(pair as u8)][..])?;
+    }
+
+    Ok(out)
+}
+
+/// Writes an `u32` in big endian byte order, checking that its reserved bits are not set
+///
+/// `reserved` is the mask of the bits the format requires to be zero. If one of them is
+/// set in `value`, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u32_with_reserved_bits};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_u32_with_reserved_bits(0x0000_1234, 0xff00_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0u8, 0u8, 0x12u8, 0x34u8][..]);
+/// ```
+pub fn be_u32_with_reserved_bits<W: Write>(value: u32, reserved: u32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if value & reserved != 0 {
+            return Err(GenError::InvalidValue);
+        }
+        be_u32(value)(out)
+    }
+}
+
+/// Writes two 4 bit values packed into an `u8`, `high` in the most significant nibble
+///
+/// If one of the values is larger than `0x0F`, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::nibbles};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(nibbles(0x4, 0x5), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0x45u8][..]);
+/// ```
+pub fn nibbles<W: Write>(high: u8, low: u8) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if high > 0x0F || low > 0x0F {
+            return Err(GenError::InvalidValue);
+        }
+        be_u8(high << 4 | low)(out)
+    }
+}
+
+/// Writes eight booleans packed into an `u8`, the first one being the most significant bit
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::flags8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let flags = [true, false, true, false, false, false, false, true];
+///   let (buf, pos) = gen(flags8(flags), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0b1010_0001u8][..]);
+/// ```
+pub fn flags8<W: Write>(bits: [bool; 8]) -> impl SerializeFn<W> {
+    be_u8(bits.iter().fold(0, |acc, &bit| (acc << 1) | bit as u8))
+}
+
+/// Writes sixteen booleans packed into an `u16` in big endian byte order, the first one
+/// being the most significant bit
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::flags16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let mut flags = [false; 16];
+///   flags[0] = true;
+///   flags[15] = true;
+///   let (buf, pos) = gen(flags16(flags), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x80u8, 0x01u8][..]);
+/// ```
+pub fn flags16<W: Write>(bits: [bool; 16]) -> impl SerializeFn<W> {
+    be_u16(bits.iter().fold(0, |acc, &bit| (acc << 1) | bit as u16))
+}
+
+/// Writes thirty two booleans packed into an `u32` in big endian byte order, the first one
+/// being the most significant bit
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::flags32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let mut flags = [false; 32];
+///   flags[0] = true;
+///   flags[31] = true;
+///   let (buf, pos) = gen(flags32(flags), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x80u8, 0u8, 0u8, 0x01u8][..]);
+/// ```
+pub fn flags32<W: Write>(bits: [bool; 32]) -> impl SerializeFn<W> {
+    be_u32(bits.iter().fold(0, |acc, &bit| (acc << 1) | bit as u32))
+}
+
+/// Writes the numeric discriminant of an enum as an `u8`
+///
+/// The enum has to implement `Into<u8>`, usually through a `From<E> for u8` impl doing
+/// the `as u8` cast for a `#[repr(u8)]` enum.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::discriminant};
+///
+/// #[repr(u8)]
+/// #[derive(Clone, Copy)]
+/// enum Kind {
+///   Start = 1,
+///   End = 0xff,
+/// }
+///
+/// impl From<Kind> for u8 {
+///   fn from(k: Kind) -> u8 {
+///     k as u8
+///   }
+/// }
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(discriminant(Kind::End), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0xffu8][..]);
+/// ```
+pub fn discriminant<E: Into<u8> + Copy, W: Write>(e: E) -> impl SerializeFn<W> {
+    be_u8(e.into())
+}
+
+/// Trait for numbers that can be written in big endian byte order with `be`
+pub trait ToBeBytes: Copy {
+    /// Length of the serialized value, in bytes
+    const LEN: usize;
+
+    /// Byte array holding the serialized value, `LEN` bytes long
+    type Bytes: AsRef<[u8]>;
+
+    /// Returns the value in big endian byte order
+    fn be_bytes(self) -> Self::Bytes;
+}
+
+/// Trait for numbers that can be written in little endian byte order with `le`
+pub trait ToLeBytes: Copy {
+    /// Length of the serialized value, in bytes
+    const LEN: usize;
+
+    /// Byte array holding the serialized value, `LEN` bytes long
+    type Bytes: AsRef<[u8]>;
+
+    /// Returns the value in little endian byte order
+    fn le_bytes(self) -> Self::Bytes;
+}
+
+// Implements ToBeBytes and ToLeBytes for the listed primitive types.
+macro_rules! to_bytes_impl(
+  ($($ty:ty),+) => (
+    $(
+      impl ToBeBytes for $ty {
+        const LEN: usize = crate::lib::std::mem::size_of::<$ty>();
+
+        type Bytes = [u8; crate::lib::std::mem::size_of::<$ty>()];
+
+        fn be_bytes(self) -> Self::Bytes {
+          self.to_be_bytes()
+        }
+      }
+
+      impl ToLeBytes for $ty {
+        const LEN: usize = crate::lib::std::mem::size_of::<$ty>();
+
+        type Bytes = [u8; crate::lib::std::mem::size_of::<$ty>()];
+
+        fn le_bytes(self) -> Self::Bytes {
+          self.to_le_bytes()
+        }
+      }
+    )+
+  );
+);
+
+to_bytes_impl!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// Writes any number implementing `ToBeBytes` in big endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be(1u32), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0u8, 0u8, 0u8, 1u8][..]);
+/// ```
+pub fn be<T: ToBeBytes, W: Write>(i: T) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        let bytes = i.be_bytes();
+        let bytes = bytes.as_ref();
+        let len = bytes.len();
+        try_write!(out, len, bytes)
+    }
+}
+
+/// Writes any number implementing `ToLeBytes` in little endian byte order to the output
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::le};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(le(1u32), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[1u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn le<T: ToLeBytes, W: Write>(i: T) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        let bytes = i.le_bytes();
+        let bytes = bytes.as_ref();
+        let len = bytes.len();
+        try_write!(out, len, bytes)
+    }
+}
+
+/// Writes a MessagePack `nil`
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_nil};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_nil(), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0xc0u8][..]);
+/// ```
+pub fn mp_nil<W: Write>() -> impl SerializeFn<W> {
+    be_u8(0xc0)
+}
+
+/// Writes a MessagePack `bool`
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_bool};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_bool(true), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0xc3u8][..]);
+/// ```
+pub fn mp_bool<W: Write>(b: bool) -> impl SerializeFn<W> {
+    be_u8(if b { 0xc3 } else { 0xc2 })
+}
+
+/// Writes an `u8` as a MessagePack integer
+///
+/// Like all the MessagePack integer serializers, this uses the smallest representation
+/// of the value, here either a positive fixint or an `uint 8`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_u8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_u8(200), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0xccu8, 200u8][..]);
+/// ```
+pub fn mp_u8<W: Write>(i: u8) -> impl SerializeFn<W> {
+    mp_u64(i as u64)
+}
+
+/// Writes an `u16` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_u16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_u16(0x1234), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0xcdu8, 0x12u8, 0x34u8][..]);
+/// ```
+pub fn mp_u16<W: Write>(i: u16) -> impl SerializeFn<W> {
+    mp_u64(i as u64)
+}
+
+/// Writes an `u32` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_u32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_u32(0x1234_5678), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &[0xceu8, 0x12u8, 0x34u8, 0x56u8, 0x78u8][..]);
+/// ```
+pub fn mp_u32<W: Write>(i: u32) -> impl SerializeFn<W> {
+    mp_u64(i as u64)
+}
+
+/// Writes an `u64` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_u64};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_u64(5), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[5u8][..]);
+/// ```
+pub fn mp_u64<W: Write>(i: u64) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if i < 0x80 {
+            be_u8(i as u8)(out)
+        } else if i <= 0xff {
+            be_u8(0xcc)(out).and_then(be_u8(i as u8))
+        } else if i <= 0xffff {
+            be_u8(0xcd)(out).and_then(be_u16(i as u16))
+        } else if i <= 0xffff_ffff {
+            be_u8(0xce)(out).and_then(be_u32(i as u32))
+        } else {
+            be_u8(0xcf)(out).and_then(be_u64(i))
+        }
+    }
+}
+
+/// Writes an `i8` as a MessagePack integer
+///
+/// Like all the MessagePack integer serializers, this uses the smallest representation
+/// of the value. Non negative values are written like unsigned ones.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_i8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_i8(-1), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0xffu8][..]);
+/// ```
+pub fn mp_i8<W: Write>(i: i8) -> impl SerializeFn<W> {
+    mp_i64(i as i64)
+}
+
+/// Writes an `i16` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_i16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_i16(-200), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0xd1u8, 0xffu8, 0x38u8][..]);
+/// ```
+pub fn mp_i16<W: Write>(i: i16) -> impl SerializeFn<W> {
+    mp_i64(i as i64)
+}
+
+/// Writes an `i32` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_i32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_i32(-100), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0xd0u8, 0x9cu8][..]);
+/// ```
+pub fn mp_i32<W: Write>(i: i32) -> impl SerializeFn<W> {
+    mp_i64(i as i64)
+}
+
+/// Writes an `i64` as a MessagePack integer
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_i64};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_i64(-0x1_0000_0000), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &[0xd3u8, 0xffu8, 0xffu8, 0xffu8, 0xffu8, 0u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn mp_i64<W: Write>(i: i64) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if i >= 0 {
+            mp_u64(i as u64)(out)
+        } else if i >= -32 {
+            be_u8(i as u8)(out)
+        } else if i >= i8::MIN as i64 {
+            be_u8(0xd0)(out).and_then(be_i8(i as i8))
+        } else if i >= i16::MIN as i64 {
+            be_u8(0xd1)(out).and_then(be_i16(i as i16))
+        } else if i >= i32::MIN as i64 {
+            be_u8(0xd2)(out).and_then(be_i32(i as i32))
+        } else {
+            be_u8(0xd3)(out).and_then(be_i64(i))
+        }
+    }
+}
+
+/// Writes an `f32` as a MessagePack `float 32`
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_f32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_f32(1.0), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &[0xcau8, 63u8, 128u8, 0u8, 0u8][..]);
+/// ```
+pub fn mp_f32<W: Write>(f: f32) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| be_u8(0xca)(out).and_then(be_f32(f))
+}
+
+/// Writes an `f64` as a MessagePack `float 64`
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_f64};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_f64(1.0), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &[0xcbu8, 63u8, 240u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..]);
+/// ```
+pub fn mp_f64<W: Write>(f: f64) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| be_u8(0xcb)(out).and_then(be_f64(f))
+}
+
+/// Writes a string as a MessagePack `str`
+///
+/// The header is a fixstr for strings shorter than 32 bytes, then a `str 8`, `str 16` or
+/// `str 32` depending on the length. Strings longer than `u32::MAX` bytes fail with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_str};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_str("abc"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"\xa3abc"[..]);
+/// ```
+pub fn mp_str<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let data = data.as_ref().as_bytes();
+        let len = data.len();
+        let mut out = mp_header(out, len, Some((0xa0, 32)), Some(0xd9), 0xda, 0xdb)?;
+
+        try_write!(out, len, data)
+    }
+}
+
+/// Writes a byte slice as a MessagePack `bin`
+///
+/// The header is a `bin 8`, `bin 16` or `bin 32` depending on the length. Slices longer
+/// than `u32::MAX` bytes fail with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::mp_bin};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(mp_bin(&[1u8, 2u8][..]), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0xc4u8, 2u8, 1u8, 2u8][..]);
+/// ```
+pub fn mp_bin<S: AsRef<[u8]>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let data = data.as_ref();
+        let len = data.len();
+        let mut out = mp_header(out, len, None, Some(0xc4), 0xc5, 0xc6)?;
+
+        try_write!(out, len, data)
+    }
+}
+
+/// Writes the header of a MessagePack `array` of `len` elements
+///
+/// The elements have to be written after it. The header is a fixarray for arrays of
+/// less than 16 elements, then an `array 16` or `array 32`. Arrays of more than `u32::MAX`
+/// elements fail with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::{mp_array_header, mp_u8}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(tuple((mp_array_header(2), mp_u8(1), mp_u8(2))), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0x92u8, 1u8, 2u8][..]);
+/// ```
+pub fn mp_array_header<W: Write>(len: usize) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| mp_header(out, len, Some((0x90, 16)), None, 0xdc, 0xdd)
+}
+
+/// Writes the header of a MessagePack `map` of `len` key-value pairs
+///
+/// The keys and values have to be written after it. The header is a fixmap for maps of
+/// less than 16 pairs, then a `map 16` or `map 32`. Maps of more than `u32::MAX` pairs
+/// fail with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::{mp_map_header, mp_str, mp_u8}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(tuple((mp_map_header(1), mp_str("a"), mp_u8(1))), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x81u8, 0xa1u8, b'a', 1u8][..]);
+/// ```
+pub fn mp_map_header<W: Write>(len: usize) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| mp_header(out, len, Some((0x80, 16)), None, 0xde, 0xdf)
+}
+
+// Writes the type byte and length of a MessagePack `str`, `bin`, `array` or `map`.
+// `fix` holds the type byte of the fix variant and the length limit under which it
+// applies, `len8`, `len16` and `len32` the type bytes of the variants with an explicit
+// length field, the types not having all of them.
+fn mp_header<W: Write>(
+    out: WriteContext<W>,
+    len: usize,
+    fix: Option<(u8, usize)>,
+    len8: Option<u8>,
+    len16: u8,
+    len32: u8,
+) -> GenResult<W> {
+    match (fix, len8) {
+        (Some((marker, limit)), _) if len < limit => be_u8(marker | len as u8)(out),
+        (_, Some(marker)) if len <= 0xff => be_u8(marker)(out).and_then(be_u8(len as u8)),
+        _ if len <= 0xffff => be_u8(len16)(out).and_then(be_u16(len as u16)),
+        _ if len as u64 <= 0xffff_ffff => be_u8(len32)(out).and_then(be_u32(len as u32)),
+        _ => Err(GenError::InvalidValue),
+    }
+}
+
+/// Writes an `u64` as an unsigned LEB128 varint
+///
+/// 7 bits are written per byte, least significant group first, with the high bit
+/// set on every byte but the last one. This takes between 1 and 10 bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::leb128_u64};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(leb128_u64(300), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0xacu8, 0x02u8][..]);
+/// ```
+pub fn leb128_u64<W: Write>(i: u64) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        let mut bytes = [0u8; 10];
+        let mut value = i;
+        let mut len = 0;
+
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes[len] = byte;
+                len += 1;
+                break;
+            }
+            bytes[len] = byte | 0x80;
+            len += 1;
+        }
+
+        try_write!(out, len, &bytes[..len])
+    }
+}
+
+/// Writes an `i32` as a Protocol Buffers `int32` varint
+///
+/// The value is sign extended to 64 bits before being written with `leb128_u64`, as
+/// required by the Protocol Buffers encoding: positive values take between 1 and 5 bytes,
+/// but negative values always take 10 bytes. Use `zigzag32` for `sint32` fields.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::varint_i32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(varint_i32(-1), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 10);
+///   assert_eq!(buf.len(), 100 - 10);
+/// }
+///
+/// assert_eq!(&buf[..10], &[0xffu8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01][..]);
+/// ```
+pub fn varint_i32<W: Write>(i: i32) -> impl SerializeFn<W> {
+    leb128_u64(i64::from(i) as u64)
+}
+
+/// Writes an `i32` as a Protocol Buffers `sint32` varint
+///
+/// The value is ZigZag encoded, `(i << 1) ^ (i >> 31)`, so that values close to 0 take
+/// few bytes whatever their sign: 0, -1, 1, -2 become 0, 1, 2, 3. The result is
+/// written with `leb128_u64`, on 1 to 5 bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::zigzag32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(zigzag32(-1), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 1);
+///   assert_eq!(buf.len(), 100 - 1);
+/// }
+///
+/// assert_eq!(&buf[..1], &[0x01u8][..]);
+/// ```
+pub fn zigzag32<W: Write>(i: i32) -> impl SerializeFn<W> {
+    leb128_u64(u64::from(((i << 1) ^ (i >> 31)) as u32))
+}
+
+/// Writes an `i64` as a byte holding a width, followed by the value in big endian byte
+/// order on that many bytes
+///
+/// The width is the smallest number of bytes, from 1 to 8, holding the value in two's
+/// complement: a width of `n` covers `-2^(8n-1)` to `2^(8n-1) - 1`. So 0 takes 1 byte,
+/// 127 and -128 take 1 byte, while 128 and -129 take 2 bytes. The output takes between
+/// 2 and 9 bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_signed_varint};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(be_signed_varint(-200), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[2u8, 0xffu8, 0x38u8][..]);
+/// ```
+pub fn be_signed_varint<W: Write>(i: i64) -> impl SerializeFn<W> {
+    let width = (1..8)
+        .find(|&n| {
+            let shift = 64 - 8 * n;
+            (i << shift) >> shift == i
+        })
+        .unwrap_or(8);
+    let mut bytes = [0u8; 9];
+    bytes[0] = width as u8;
+    bytes[1..=width].copy_from_slice(&i.to_be_bytes()[8 - width..]);
+    let len = width + 1;
+
+    move |mut out: WriteContext<W>| try_write!(out, len, &bytes[..len])
+}
+
+/// Writes the non-zero values of an array of `u32`, with their indices
+///
+/// For each non-zero value, this writes its index plus one as a LEB128 varint (see
+/// `leb128_u64`), then the value as a big endian `u32`. A varint 0 ends the list: since
+/// written indices start at 1, it cannot be mistaken for an entry.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::sparse_array_be_u32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(sparse_array_be_u32(&[0, 0, 7, 0][..]), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &[3u8, 0u8, 0u8, 0u8, 7u8, 0u8][..]);
+/// ```
+pub fn sparse_array_be_u32<S: AsRef<[u32]>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        for (index, &value) in data.as_ref().iter().enumerate() {
+            if value != 0 {
+                out = leb128_u64(index as u64 + 1)(out).and_then(be_u32(value))?;
+            }
+        }
+
+        leb128_u64(0)(out)
+    }
+}
+
+/// Writes a Protocol Buffers field key, made of a field number and a wire type
+///
+/// The key is the varint `(field_number << 3) | wire_type`. Field numbers have to be
+/// between 1 and `2^29 - 1`, and wire types lower than 8, otherwise this fails with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::pb_tag};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(pb_tag(16, 2), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x82u8, 0x01u8][..]);
+/// ```
+pub fn pb_tag<W: Write>(field_number: u32, wire_type: u8) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if field_number == 0 || field_number >= 1 << 29 || wire_type >= 8 {
+            return Err(GenError::InvalidValue);
+        }
+
+        leb128_u64(u64::from(field_number) << 3 | u64::from(wire_type))(out)
+    }
+}
+
+/// Writes a Protocol Buffers varint field (wire type 0)
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::pb_varint_field};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(pb_varint_field(1, 150), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0x08u8, 0x96u8, 0x01u8][..]);
+/// ```
+pub fn pb_varint_field<W: Write>(field_number: u32, value: u64) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| pb_tag(field_number, 0)(out).and_then(leb128_u64(value))
+}
+
+/// Writes a Protocol Buffers length-delimited field (wire type 2)
+///
+/// The length prefix is a varint, so its size is only known once `body` is
+/// serialized: `body` is written to a temporary `Vec<u8>` first.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::pb_len_delimited_field, combinator::string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(pb_len_delimited_field(2, string("testing")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &b"\x12\x07testing"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn pb_len_delimited_field<F, W: Write>(field_number: u32, body: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<Vec<u8>>,
+{
+    move |out: WriteContext<W>| {
+        let data = crate::internal::gen_simple(&body, Vec::new())?;
+        let len = data.len();
+        let out = pb_tag(field_number, 2)(out)?;
+        let mut out = leb128_u64(len as u64)(out)?;
+
+        try_write!(out, len, &data[..])
+    }
+}
+
+/// Wrapper for a number that is serialized in big endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::Be};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(Be(0x1234u16).gen(), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x12u8, 0x34u8][..]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Be<T>(pub T);
+
+/// Wrapper for a number that is serialized in little endian byte order
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::Le};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(Le(0x1234u16).gen(), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0x34u8, 0x12u8][..]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Le<T>(pub T);
+
+// Generates the `gen` method of an endianness wrapper for each listed type,
+// dispatching to the matching serializing function.
+macro_rules! endian_wrapper_impl(
+  ($wrapper:ident, $order:expr; $($ty:ty => $f:ident),+) => (
+    $(
+      impl $wrapper<$ty> {
+        #[doc = "Returns a serializer writing the wrapped `"]
+        #[doc = stringify!($ty)]
+        #[doc = "` in "]
+        #[doc = $order]
+        #[doc = " byte order"]
+        pub fn gen<W: Write>(&self) -> impl SerializeFn<W> {
+          $f(self.0)
+        }
+      }
+    )+
+  );
+);
+
+endian_wrapper_impl!(Be, "big endian";
+    u8 => be_u8, u16 => be_u16, u32 => be_u32, u64 => be_u64, u128 => be_u128,
+    i8 => be_i8, i16 => be_i16, i32 => be_i32, i64 => be_i64, i128 => be_i128,
+    f32 => be_f32, f64 => be_f64
+);
+
+endian_wrapper_impl!(Le, "little endian";
+    u8 => le_u8, u16 => le_u16, u32 => le_u32, u64 => le_u64, u128 => le_u128,
+    i8 => le_i8, i16 => le_i16, i32 => le_i32, i64 => le_i64, i128 => le_i128,
+    f32 => le_f32, f64 => le_f64
+);
+
+impl<W: Write, T: ToBeBytes> MaxSize<W> for Be<T> {
+    fn max_size(&self) -> usize {
+        T::LEN
+    }
+
+    fn serialize(&self, w: WriteContext<W>) -> GenResult<W> {
+        be(self.0)(w)
+    }
+}
+
+impl<W: Write, T: ToLeBytes> MaxSize<W> for Le<T> {
+    fn max_size(&self) -> usize {
+        T::LEN
+    }
+
+    fn serialize(&self, w: WriteContext<W>) -> GenResult<W> {
+        le(self.0)(w)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::gen;
+
+    #[test]
+    fn test_endian_wrappers() {
+        let mut buf = [0u8; 2];
+        gen(Be(0x1234u16).gen(), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+
+        let mut buf = [0u8; 2];
+        gen(Le(0x1234u16).gen(), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x34, 0x12]);
+
+        let mut buf = [0u8; 8];
+        gen(Be(-2i64).gen(), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]);
+
+        let mut buf = [0u8; 4];
+        gen(Le(1.0f32).gen(), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0, 0, 0x80, 0x3f]);
+    }
+
+    #[test]
+    fn test_flags() {
+        let mut buf = [0u8; 1];
+        gen(
+            flags8([true, false, false, false, false, false, true, true]),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(buf, [0b1000_0011]);
+
+        gen(flags8([false; 8]), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x00]);
+
+        gen(flags8([true; 8]), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff]);
+
+        let mut buf = [0u8; 2];
+        let mut bits = [false; 16];
+        bits[7] = true;
+        bits[8] = true;
+        gen(flags16(bits), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x01, 0x80]);
+
+        gen(flags16([true; 16]), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff, 0xff]);
+
+        let mut buf = [0u8; 4];
+        gen(flags32([false; 32]), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0, 0, 0, 0]);
+
+        gen(flags32([true; 32]), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_generic_be_le() {
+        use crate::internal::gen_array;
+
+        assert_eq!(
+            gen_array::<2, _>(be(0x1234u16)).unwrap(),
+            gen_array::<2, _>(be_u16(0x1234)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<8, _>(be(0x0102_0304_0506_0708u64)).unwrap(),
+            gen_array::<8, _>(be_u64(0x0102_0304_0506_0708)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<4, _>(be(1.5f32)).unwrap(),
+            gen_array::<4, _>(be_f32(1.5)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<1, _>(be(-2i8)).unwrap(),
+            gen_array::<1, _>(be_i8(-2)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<2, _>(le(0x1234u16)).unwrap(),
+            gen_array::<2, _>(le_u16(0x1234)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<8, _>(le(0x0102_0304_0506_0708u64)).unwrap(),
+            gen_array::<8, _>(le_u64(0x0102_0304_0506_0708)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<4, _>(le(1.5f32)).unwrap(),
+            gen_array::<4, _>(le_f32(1.5)).unwrap()
+        );
+        assert_eq!(
+            gen_array::<8, _>(le(-2.25f64)).unwrap(),
+            gen_array::<8, _>(le_f64(-2.25)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generic_be_le_wide() {
+        use crate::internal::gen_array;
+
+        // a number wider than the primitive types, stored as big endian 64 bit limbs
+        #[derive(Clone, Copy)]
+        struct U256([u64; 4]);
+
+        impl ToBeBytes for U256 {
+            const LEN: usize = 32;
+            type Bytes = [u8; 32];
+
+            fn be_bytes(self) -> [u8; 32] {
+                let mut bytes = [0u8; 32];
+                for (chunk, limb) in bytes.chunks_mut(8).zip(self.0.iter()) {
+                    chunk.copy_from_slice(&limb.to_be_bytes());
+                }
+                bytes
+            }
+        }
+
+        let mut expected = [0u8; 32];
+        expected[0] = 0x01;
+        expected[31] = 0x02;
+        assert_eq!(
+            gen_array::<32, _>(be(U256([0x0100_0000_0000_0000, 0, 0, 2]))).unwrap(),
+            expected
+        );
+
+        let mut buf = [0u8; 31];
+        let res = gen(be(U256([0; 4])), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_mp_integers() {
+        let mut buf = [0u8; 9];
+
+        let (_, pos) = gen(mp_u8(5), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0x05]);
+
+        let (_, pos) = gen(mp_u8(0x7f), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0x7f]);
+
+        let (_, pos) = gen(mp_u8(0x80), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xcc, 0x80]);
+
+        let (_, pos) = gen(mp_u64(u64::MAX), &mut buf[..]).unwrap();
+        assert_eq!(
+            &buf[..pos as usize],
+            &[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+
+        let (_, pos) = gen(mp_i8(5), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0x05]);
+
+        let (_, pos) = gen(mp_i8(-32), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xe0]);
+
+        let (_, pos) = gen(mp_i8(-33), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xd0, 0xdf]);
+
+        let (_, pos) = gen(mp_i32(i32::MIN), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xd2, 0x80, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_mp_str() {
+        let mut buf = [0u8; 64];
+
+        let (_, pos) = gen(mp_str("hello"), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &b"\xa5hello"[..]);
+
+        let (_, pos) = gen(mp_str(""), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xa0]);
+
+        let long = "a".repeat(32);
+        let (_, pos) = gen(mp_str(&long), &mut buf[..]).unwrap();
+        assert_eq!(pos, 34);
+        assert_eq!(&buf[..2], &[0xd9, 32]);
+        assert_eq!(&buf[2..34], long.as_bytes());
+    }
+
+    #[test]
+    fn test_mp_headers() {
+        let mut buf = [0u8; 5];
+
+        let (_, pos) = gen(mp_array_header(15), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0x9f]);
+
+        let (_, pos) = gen(mp_array_header(16), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xdc, 0x00, 0x10]);
+
+        let (_, pos) = gen(mp_array_header(0x1_0000), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xdd, 0x00, 0x01, 0x00, 0x00]);
+
+        let (_, pos) = gen(mp_map_header(16), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0xde, 0x00, 0x10]);
+    }
+
+    #[test]
+    fn test_leb128_u64() {
+        let cases: &[(u64, &[u8])] = &[
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (624_485, &[0xe5, 0x8e, 0x26]),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+        ];
+
+        for (value, expected) in cases {
+            let mut buf = [0u8; 10];
+            let (_, pos) = gen(leb128_u64(*value), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], *expected);
+        }
+
+        let mut buf = [0u8; 1];
+        assert!(matches!(
+            gen(leb128_u64(128), &mut buf[..]),
+            Err(GenError::BufferTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn test_pb_tag() {
+        let mut buf = [0u8; 5];
+        let (_, pos) = gen(pb_tag(1, 0), &mut buf[..]).unwrap();
+        assert_eq!(pos, 1);
+        assert_eq!(buf[0], 0x08);
+
+        let (_, pos) = gen(pb_tag((1 << 29) - 1, 5), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf, &[0xfd, 0xff, 0xff, 0xff, 0x0f]);
+
+        for &(field_number, wire_type) in &[(0, 0), (1 << 29, 0), (1, 8)] {
+            let res = gen(pb_tag(field_number, wire_type), &mut buf[..]);
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_pb_len_delimited_field() {
+        use crate::sequence::tuple;
+
+        let mut buf = [0u8; 8];
+        let (out, pos) = gen(
+            pb_len_delimited_field(3, tuple((pb_varint_field(1, 150), pb_tag(2, 0)))),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 6);
+        assert_eq!(out.len(), 2);
+        assert_eq!(&buf[..6], &[0x1a, 0x04, 0x08, 0x96, 0x01, 0x10]);
+    }
+
+    #[test]
+    fn test_discriminant() {
+        #[repr(u8)]
+        #[derive(Clone, Copy)]
+        enum Op {
+            Nop = 0,
+            Load = 0x10,
+            Store = 0x11,
+            Halt = 0xfe,
+        }
+
+        impl From<Op> for u8 {
+            fn from(op: Op) -> u8 {
+                op as u8
+            }
+        }
+
+        let ops = [Op::Nop, Op::Load, Op::Store, Op::Halt];
+        let mut buf = [0u8; 4];
+        for (op, b) in ops.iter().zip(buf.chunks_mut(1)) {
+            gen(discriminant(*op), b).unwrap();
+        }
+        assert_eq!(buf, [0x00, 0x10, 0x11, 0xfe]);
+    }
+
+    #[test]
+    fn test_u64_split() {
+        use crate::internal::gen_array;
+
+        let value = 0x0123_4567_89ab_cdefu64;
+        let (hi, lo) = ((value >> 32) as u32, value as u32);
+
+        assert_eq!(
+            gen_array::<8, _>(be_u64_split(hi, lo)).unwrap(),
+            value.to_be_bytes()
+        );
+        assert_eq!(
+            gen_array::<8, _>(le_u64_split(hi, lo)).unwrap(),
+            value.to_le_bytes()
+        );
+        assert_eq!(
+            gen_array::<8, _>(be_u64_split_swapped(hi, lo)).unwrap(),
+            [0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67]
+        );
+        assert_eq!(
+            gen_array::<8, _>(le_u64_split_swapped(hi, lo)).unwrap(),
+            [0x67, 0x45, 0x23, 0x01, 0xef, 0xcd, 0xab, 0x89]
+        );
+    }
+
+    #[test]
+    fn test_bcd() {
+        let mut buf = [0xffu8; 2];
+        gen(bcd(1234, 2), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x12, 0x34]);
+
+        gen(bcd(7, 2), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x00, 0x07]);
+
+        gen(bcd(9999, 2), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x99, 0x99]);
+
+        gen(bcd_swapped(1234, 2), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0x21, 0x43]);
+
+        assert!(matches!(
+            gen(bcd(10000, 2), &mut buf[..]),
+            Err(GenError::InvalidValue)
+        ));
+
+        let mut buf = [0xffu8; 11];
+        gen(bcd(u64::MAX, 11), &mut buf[..]).unwrap();
+        assert_eq!(
+            buf,
+            [0x00, 0x18, 0x44, 0x67, 0x44, 0x07, 0x37, 0x09, 0x55, 0x16, 0x15]
+        );
+    }
+
+    #[test]
+    fn test_saturating() {
+        use crate::internal::gen_array;
+
+        assert_eq!(gen_array(be_u16_saturating(0x1234)).unwrap(), [0x12, 0x34]);
+        assert_eq!(gen_array(be_u16_saturating(0xffff)).unwrap(), [0xff, 0xff]);
+        assert_eq!(
+            gen_array(be_u16_saturating(u32::MAX)).unwrap(),
+            [0xff, 0xff]
+        );
+        assert_eq!(
+            gen_array(le_u16_saturating(0x1_2345)).unwrap(),
+            [0xff, 0xff]
+        );
+
+        assert_eq!(
+            gen_array(be_u24_saturating(0x12_3456)).unwrap(),
+            [0x12, 0x34, 0x56]
+        );
+        assert_eq!(
+            gen_array(le_u24_saturating(0x12_3456)).unwrap(),
+            [0x56, 0x34, 0x12]
+        );
+        assert_eq!(
+            gen_array(be_u24_saturating(0x1234_5678)).unwrap(),
+            [0xff; 3]
+        );
+
+        assert_eq!(
+            gen_array(be_u32_saturating(0x0102_0304)).unwrap(),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            gen_array(le_u32_saturating(0x0102_0304)).unwrap(),
+            [4, 3, 2, 1]
+        );
+        assert_eq!(gen_array(le_u32_saturating(u64::MAX)).unwrap(), [0xff; 4]);
+    }
+
+    #[test]
+    fn test_sparse_array_be_u32() {
+        let mut sparse = [0u32; 200];
+        sparse[0] = 1;
+        sparse[150] = 0x0102_0304;
+
+        let mut buf = [0u8; 12];
+        let (out, pos) = gen(sparse_array_be_u32(&sparse[..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 12);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[0x01, 0, 0, 0, 1, 0x97, 0x01, 1, 2, 3, 4, 0x00]);
+
+        let mut buf = [0u8; 16];
+        let (_, pos) = gen(sparse_array_be_u32(&[5, 6, 7][..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 16);
+        assert_eq!(&buf, &[1, 0, 0, 0, 5, 2, 0, 0, 0, 6, 3, 0, 0, 0, 7, 0]);
+
+        let mut buf = [0xffu8; 1];
+        let (_, pos) = gen(sparse_array_be_u32(&[0u32; 8][..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 1);
+        assert_eq!(buf, [0]);
+    }
+
+    #[test]
+    fn test_i128() {
+        use crate::internal::gen_array;
+
+        assert_eq!(gen_array(be_i128(-1)).unwrap(), [0xff; 16]);
+        assert_eq!(gen_array(le_i128(-1)).unwrap(), [0xff; 16]);
+        assert_eq!(gen_array(be_i128(0)).unwrap(), [0; 16]);
+        assert_eq!(gen_array(le_i128(0)).unwrap(), [0; 16]);
+
+        let mut min_be = [0u8; 16];
+        min_be[0] = 0x80;
+        let mut min_le = [0u8; 16];
+        min_le[15] = 0x80;
+        assert_eq!(gen_array(be_i128(i128::MIN)).unwrap(), min_be);
+        assert_eq!(gen_array(le_i128(i128::MIN)).unwrap(), min_le);
+
+        let value = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10i128;
+        assert_eq!(gen_array(be_i128(value)).unwrap(), value.to_be_bytes());
+        assert_eq!(gen_array(le_i128(value)).unwrap(), value.to_le_bytes());
+        assert_eq!(gen_array(be(value)).unwrap(), value.to_be_bytes());
+        assert_eq!(gen_array(le(u128::MAX)).unwrap(), [0xff; 16]);
+    }
+
+    #[test]
+    fn test_endianness() {
+        use crate::internal::gen_array;
+        use crate::sequence::tuple;
+
+        let header = |endian| {
+            tuple((
+                endian_u16(endian, 0x0102),
+                endian_u24(endian, 0x03_0405),
+                endian_i32(endian, -2),
+                endian_f32(endian, 1.0),
+            ))
+        };
+
+        assert_eq!(
+            gen_array::<13, _>(header(Endianness::Big)).unwrap(),
+            [1, 2, 3, 4, 5, 0xff, 0xff, 0xff, 0xfe, 0x3f, 0x80, 0, 0]
+        );
+        assert_eq!(
+            gen_array::<13, _>(header(Endianness::Little)).unwrap(),
+            [2, 1, 5, 4, 3, 0xfe, 0xff, 0xff, 0xff, 0, 0, 0x80, 0x3f]
+        );
+
+        let mut native = [0u8; 8];
+        gen(
+            endian_u64(Endianness::Native, 0x0102_0304_0506_0708),
+            &mut native[..],
+        )
+        .unwrap();
+        assert_eq!(native, 0x0102_0304_0506_0708u64.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_be_f80() {
+        let mut buf = [0u8; 10];
+
+        let cases: &[(f64, [u8; 10])] = &[
+            (44100.0, [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]),
+            (48000.0, [0x40, 0x0e, 0xbb, 0x80, 0, 0, 0, 0, 0, 0]),
+            (-2.0, [0xc0, 0x00, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+            (0.0, [0; 10]),
+            (-0.0, [0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            (f64::INFINITY, [0x7f, 0xff, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+            (f64::MIN_POSITIVE, [0x3c, 0x01, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+            // smallest subnormal, 2^-1074
+            (f64::from_bits(1), [0x3b, 0xcd, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+        ];
+
+        for (value, expected) in cases {
+            let (_, pos) = gen(be_f80(*value), &mut buf[..]).unwrap();
+            assert_eq!(pos, 10);
+            assert_eq!(&buf, expected, "encoding {:e}", value);
+        }
+
+        gen(be_f80(f64::NAN), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..2], &[0x7f, 0xff]);
+        assert_ne!(&buf[2..], &[0x80, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_float_arrays() {
+        let data = [1.0f64, -2.5, 0.1];
+
+        let mut buf = [0u8; 24];
+        let (out, pos) = gen(be_f64_array(&data[..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 24);
+        assert!(out.is_empty());
+        for (chunk, f) in buf.chunks(8).zip(&data) {
+            assert_eq!(chunk, f.to_be_bytes());
+        }
+
+        let (_, pos) = gen(le_f64_array(data), &mut buf[..]).unwrap();
+        assert_eq!(pos, 24);
+        for (chunk, f) in buf.chunks(8).zip(&data) {
+            assert_eq!(chunk, f.to_le_bytes());
+        }
+
+        let (_, pos) = gen(be_f32_array([1.0f32, -2.0]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 8);
+        assert_eq!(&buf[..8], &[0x3f, 0x80, 0, 0, 0xc0, 0, 0, 0]);
+        let (_, pos) = gen(le_f32_array([1.0f32, -2.0]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 8);
+        assert_eq!(&buf[..8], &[0, 0, 0x80, 0x3f, 0, 0, 0, 0xc0]);
+
+        let res = gen(be_f64_array(data), &mut buf[..20]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+
+        // several chunks, the last one partial
+        let data: [f32; 100] = core::array::from_fn(|i| i as f32);
+        let mut buf = [0u8; 400];
+        let (_, pos) = gen(le_f32_array(&data[..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 400);
+        for (chunk, f) in buf.chunks(4).zip(&data) {
+            assert_eq!(chunk, f.to_le_bytes());
+        }
+
+        let res = gen(le_f32_array(&data[..]), &mut buf[..300]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(100))));
+    }
+
+    #[test]
+    fn test_f32_bits() {
+        let mut from_bits = [0u8; 4];
+        let mut from_float = [0u8; 4];
+
+        for &(bits, value) in &[(0x3f80_0000, 1.0f32), (0xc120_0000, -10.0), (0, 0.0)] {
+            gen(u32_as_f32_be(bits), &mut from_bits[..]).unwrap();
+            gen(be_f32(value), &mut from_float[..]).unwrap();
+            assert_eq!(from_bits, from_float);
+
+            gen(f32_bits_be(value), &mut from_float[..]).unwrap();
+            assert_eq!(from_float, bits.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_be_signed_varint() {
+        let mut buf = [0u8; 9];
+
+        let cases: &[(i64, &[u8])] = &[
+            (0, &[1, 0]),
+            (1, &[1, 1]),
+            (127, &[1, 0x7f]),
+            (128, &[2, 0, 0x80]),
+            (-1, &[1, 0xff]),
+            (-128, &[1, 0x80]),
+            (-129, &[2, 0xff, 0x7f]),
+            (0x12_3456, &[3, 0x12, 0x34, 0x56]),
+            (
+                i64::MAX,
+                &[8, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (i64::MIN, &[8, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+        ];
+
+        for (value, expected) in cases {
+            let (_, pos) = gen(be_signed_varint(*value), &mut buf[..]).unwrap();
+            assert_eq!(pos, expected.len() as u64, "width of {}", value);
+            assert_eq!(&buf[..expected.len()], *expected);
+        }
+
+        let res = gen(be_signed_varint(128), &mut buf[..2]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_varint_i32_zigzag32() {
+        let cases: &[(i32, &[u8], &[u8])] = &[
+            (0, &[0x00], &[0x00]),
+            (1, &[0x01], &[0x02]),
+            (
+                -1,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+                &[0x01],
+            ),
+            (150, &[0x96, 0x01], &[0xac, 0x02]),
+            (
+                -2,
+                &[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+                &[0x03],
+            ),
+            (
+                i32::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0x07],
+                &[0xfe, 0xff, 0xff, 0xff, 0x0f],
+            ),
+            (
+                i32::MIN,
+                &[0x80, 0x80, 0x80, 0x80, 0xf8, 0xff, 0xff, 0xff, 0xff, 0x01],
+                &[0xff, 0xff, 0xff, 0xff, 0x0f],
+            ),
+        ];
+
+        for (value, int32, sint32) in cases {
+            let mut buf = [0u8; 10];
+            let (_, pos) = gen(varint_i32(*value), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], *int32);
+            let (_, pos) = gen(zigzag32(*value), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], *sint32);
+        }
+    }
+
+    #[test]
+    fn test_rational() {
+        let mut buf = [0u8; 8];
+        gen(be_rational(72, 1), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0, 0, 0, 72, 0, 0, 0, 1]);
+        gen(le_rational(0x0102_0304, 0x0506_0708), &mut buf[..]).unwrap();
+        assert_eq!(buf, [4, 3, 2, 1, 8, 7, 6, 5]);
+        gen(be_srational(1, -2), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xfe]);
+        gen(le_srational(i32::MIN, i32::MAX), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0, 0, 0, 0x80, 0xff, 0xff, 0xff, 0x7f]);
+
+        for res in [
+            gen(be_rational(1, 0), &mut buf[..]).map(|(_, pos)| pos),
+            gen(le_rational(0, 0), &mut buf[..]).map(|(_, pos)| pos),
+            gen(be_srational(-1, 0), &mut buf[..]).map(|(_, pos)| pos),
+            gen(le_srational(1, 0), &mut buf[..]).map(|(_, pos)| pos),
+        ] {
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+        }
+
+        assert!(matches!(
+            gen(be_rational(1, 2), &mut buf[..7]),
+            Err(GenError::BufferTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn test_nibbles() {
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 2];
+        let (_, pos) = gen(pair(nibbles(0xF, 0x0), nibbles(0x0, 0xF)), &mut buf[..]).unwrap();
+        assert_eq!(pos, 2);
+        assert_eq!(buf, [0xF0, 0x0F]);
+
+        for &(high, low) in &[(0x10, 0), (0, 0x10), (0xFF, 0xFF)] {
+            assert!(matches!(
+                gen(nibbles(high, low), &mut buf[..]),
+                Err(GenError::InvalidValue)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_be_u32_with_reserved_bits() {
+        let mut buf = [0u8; 4];
+        let (_, pos) = gen(
+            be_u32_with_reserved_bits(0x7fff_fffe, 0x8000_0001),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 4);
+        assert_eq!(buf, [0x7f, 0xff, 0xff, 0xfe]);
+
+        gen(be_u32_with_reserved_bits(u32::MAX, 0), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff; 4]);
+
+        for &value in &[0x8000_0000, 0x0000_0001, 0xffff_ffff] {
+            let res = gen(be_u32_with_reserved_bits(value, 0x8000_0001), &mut buf[..]);
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+        }
+    }
+}
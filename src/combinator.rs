@@ -12,6 +12,9 @@ macro_rules! try_write(($out:ident, $len:ident, $data:expr) => (
 
 /// Writes a byte slice to the output
 ///
+/// `data` is moved into the serializer, so owned values like a `Vec<u8>` or a
+/// `Cow<[u8]>` can be used and are kept alive as long as the serializer.
+///
 /// ```rust
 /// use cookie_factory::{gen, combinator::slice};
 ///
@@ -33,6 +36,9 @@ pub fn slice<S: AsRef<[u8]>, W: Write>(data: S) -> impl SerializeFn<W> {
 
 /// Writes a string slice to the output
 ///
+/// `data` is moved into the serializer, so owned values like a `String` or a
+/// `Cow<str>` can be used and are kept alive as long as the serializer.
+///
 /// ```rust
 /// use cookie_factory::{gen, combinator::string};
 ///
@@ -52,6 +58,216 @@ pub fn string<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
     move |mut out: WriteContext<W>| try_write!(out, len, data.as_ref().as_bytes())
 }
 
+/// Writes the length in bytes of a string as a big endian `u32`, followed by the string
+///
+/// The length counts the bytes of the UTF-8 encoding, not the characters. Strings longer
+/// than `u32::MAX` bytes fail with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::len_prefixed_string_be_u32};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(len_prefixed_string_be_u32("abcd"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"\x00\x00\x00\x04abcd"[..]);
+/// ```
+pub fn len_prefixed_string_be_u32<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let data = data.as_ref();
+        if data.len() as u64 > u64::from(u32::MAX) {
+            return Err(GenError::InvalidValue);
+        }
+
+        let out = crate::bytes::be_u32(data.len() as u32)(out)?;
+        string(data)(out)
+    }
+}
+
+/// Writes a string in a field of `width` bytes, padded with zero bytes
+///
+/// The string can fill the whole field, in which case no zero byte is written. Strings
+/// longer than `width` bytes fail with `GenError::InvalidValue`, before writing anything.
+/// See `null_terminated_padded_string` for fields that always end with a zero byte.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::null_padded_string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(null_padded_string("abc", 6), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &b"abc\x00\x00\x00"[..]);
+/// ```
+pub fn null_padded_string<S: AsRef<str>, W: Write>(data: S, width: usize) -> impl SerializeFn<W> {
+    padded_string(data, width, 0)
+}
+
+/// Writes a string in a field of `width` bytes, padded with zero bytes and always ending
+/// with at least one
+///
+/// Strings longer than `width - 1` bytes fail with `GenError::InvalidValue`, before
+/// writing anything.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::null_terminated_padded_string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(null_terminated_padded_string("abc", 4), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abc\x00"[..]);
+/// ```
+pub fn null_terminated_padded_string<S: AsRef<str>, W: Write>(
+    data: S,
+    width: usize,
+) -> impl SerializeFn<W> {
+    padded_string(data, width, 1)
+}
+
+/// Writes a string with its ASCII letters converted to lowercase
+///
+/// Non-ASCII characters are written unchanged, so the output is always valid UTF-8 with
+/// the same length as the input.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::ascii_lowercase};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(ascii_lowercase("HeLLo"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"hello"[..]);
+/// ```
+pub fn ascii_lowercase<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    ascii_case_mapped(data, u8::to_ascii_lowercase)
+}
+
+/// Writes a string with its ASCII letters converted to uppercase
+///
+/// Non-ASCII characters are written unchanged, so the output is always valid UTF-8 with
+/// the same length as the input.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::ascii_uppercase};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(ascii_uppercase("HeLLo"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"HELLO"[..]);
+/// ```
+pub fn ascii_uppercase<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    ascii_case_mapped(data, u8::to_ascii_uppercase)
+}
+
+// Writes the bytes of `data` mapped by `map`, through a buffer on the stack
+fn ascii_case_mapped<S: AsRef<str>, W: Write>(data: S, map: fn(&u8) -> u8) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        let data = data.as_ref().as_bytes();
+        let mut chunk = [0u8; 64];
+        let mut remaining = data.len();
+
+        for part in data.chunks(chunk.len()) {
+            for (dst, src) in chunk.iter_mut().zip(part) {
+                *dst = map(src);
+            }
+            let len = part.len();
+            out = match out.write(&chunk[..len]) {
+                Err(io) => return Err(GenError::IoError(io)),
+                Ok(n) if n < len => return Err(GenError::BufferTooSmall(remaining - n)),
+                Ok(_) => out,
+            };
+            remaining -= len;
+        }
+
+        Ok(out)
+    }
+}
+
+// Writes `data` followed by zero bytes up to `width`, with at least `min_padding` of them
+fn padded_string<S: AsRef<str>, W: Write>(
+    data: S,
+    width: usize,
+    min_padding: usize,
+) -> impl SerializeFn<W> {
+    const ZEROS: [u8; 64] = [0; 64];
+
+    move |out: WriteContext<W>| {
+        let data = data.as_ref().as_bytes();
+        if data.len() + min_padding > width {
+            return Err(GenError::InvalidValue);
+        }
+
+        let mut out = slice(data)(out)?;
+        let mut padding = width - data.len();
+        while padding > 0 {
+            let len = crate::lib::std::cmp::min(padding, ZEROS.len());
+            out = try_write!(out, len, &ZEROS[..len])?;
+            padding -= len;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Adds a byte slice to a `Vectored` output by reference, without copying it
+///
+/// `slice` works with `Vectored` too, but copies the data to its owned buffer.
+///
+/// ```rust
+/// use cookie_factory::{gen_vectored, combinator::slice_ref};
+///
+/// let data = b"abcd";
+/// let v = gen_vectored(slice_ref(&data[..])).unwrap();
+///
+/// assert_eq!(v.io_slices()[0].as_ptr(), data.as_ptr());
+/// ```
+#[cfg(feature = "std")]
+pub fn slice_ref<'a>(data: &'a [u8]) -> impl SerializeFn<Vectored<'a>> {
+    move |mut out: WriteContext<Vectored<'a>>| {
+        out.write.push_slice(data);
+        out.position += data.len() as u64;
+        Ok(out)
+    }
+}
+
+/// Adds a string to a `Vectored` output by reference, without copying it
+///
+/// `string` works with `Vectored` too, but copies the data to its owned buffer.
+///
+/// ```rust
+/// use cookie_factory::{gen_vectored, combinator::string_ref};
+///
+/// let v = gen_vectored(string_ref("abcd")).unwrap();
+///
+/// assert_eq!(&v.io_slices()[0][..], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn string_ref(data: &str) -> impl SerializeFn<Vectored<'_>> {
+    slice_ref(data.as_bytes())
+}
+
 /// Writes an hex string to the output
 #[cfg(feature = "std")]
 /// ```rust
@@ -74,6 +290,173 @@ pub fn hex<S: crate::lib::std::fmt::UpperHex, W: Write>(data: S) -> impl Seriali
     }
 }
 
+/// Writes a float as text, with `precision` digits after the decimal point
+///
+/// This uses the same formatting as `format!("{:.N}", value)`, rounding to the closest
+/// value.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::text_float};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(text_float(3.14159, 2), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"3.14"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn text_float<W: Write>(value: f64, precision: usize) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| match write!(out, "{:.*}", precision, value) {
+        Err(io) => Err(GenError::IoError(io)),
+        Ok(()) => Ok(out),
+    }
+}
+
+/// Writes a float as a JSON number, with the shortest representation that reads back as
+/// the same value
+///
+/// Integer values are written without a fractional part, and an exponent is used when it
+/// makes the number shorter. JSON has no representation for `NaN` and infinities, so
+/// they fail with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::json_number};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(json_number(0.25), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"0.25"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn json_number<W: Write>(value: f64) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if !value.is_finite() {
+            return Err(GenError::InvalidValue);
+        }
+
+        let plain = format!("{}", value);
+        let exponent = format!("{:e}", value);
+        if exponent.len() < plain.len() {
+            string(exponent)(out)
+        } else {
+            string(plain)(out)
+        }
+    }
+}
+
+/// Writes `true_word` if `value` is true, `false_word` otherwise
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::text_bool};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(text_bool(false, "yes", "no"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &b"no"[..]);
+/// ```
+pub fn text_bool<S: AsRef<str>, W: Write>(
+    value: bool,
+    true_word: S,
+    false_word: S,
+) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let word = if value { &true_word } else { &false_word };
+        string(word)(out)
+    }
+}
+
+/// Writes `value` in decimal, right aligned in a field of `width` bytes padded with `fill`
+///
+/// `fill` is usually `b' '` for text tables or `b'0'` for zero padded fields. If the
+/// number has more than `width` digits, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::number_right_aligned};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(number_right_aligned(42, 5, b' '), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"   42"[..]);
+/// ```
+pub fn number_right_aligned<W: Write>(value: u64, width: usize, fill: u8) -> impl SerializeFn<W> {
+    right_aligned(value, 10, width, fill)
+}
+
+/// Writes `value` in octal, right aligned in a field of `width` bytes padded with `fill`
+///
+/// This is the encoding of tar header fields, with `b'0'` as `fill`. If the number has
+/// more than `width` digits, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::octal_right_aligned};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(octal_right_aligned(0o644, 7, b'0'), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 7);
+///   assert_eq!(buf.len(), 100 - 7);
+/// }
+///
+/// assert_eq!(&buf[..7], &b"0000644"[..]);
+/// ```
+pub fn octal_right_aligned<W: Write>(value: u64, width: usize, fill: u8) -> impl SerializeFn<W> {
+    right_aligned(value, 8, width, fill)
+}
+
+// Writes the digits of `value` in `radix`, preceded by enough `fill` bytes to get `width` bytes
+fn right_aligned<W: Write>(value: u64, radix: u64, width: usize, fill: u8) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        // enough for the 22 octal digits of u64::MAX
+        let mut digits = [0u8; 32];
+        let mut start = digits.len();
+        let mut n = value;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (n % radix) as u8;
+            n /= radix;
+            if n == 0 {
+                break;
+            }
+        }
+
+        let digits = &digits[start..];
+        if digits.len() > width {
+            return Err(GenError::InvalidValue);
+        }
+
+        let padding = [fill; 32];
+        let mut remaining = width - digits.len();
+        while remaining > 0 {
+            let len = crate::lib::std::cmp::min(remaining, padding.len());
+            out = try_write!(out, len, &padding[..len])?;
+            remaining -= len;
+        }
+
+        let len = digits.len();
+        try_write!(out, len, digits)
+    }
+}
+
 /// Skips over some input bytes without writing anything
 ///
 /// ```rust
@@ -148,133 +531,3828 @@ where
     move |w: WriteContext<W>| W::reserve_write_use(w, reserved, &gen, &before)
 }
 
-//missing combinators:
-//or
-//empty
-//then
-//stream
-//length_value
-//text print
-//text upperhex
-//text lowerhex
+/// Writes the length of the output of `body` as a big endian `u32`, followed by the output
+/// of `body`
+///
+/// The length field is reserved with `back_to_the_buffer` and filled once `body` was
+/// written, so `body` is only applied once, but the output must implement `BackToTheBuffer`.
+/// `sized_group!(|len| be_u32(len as u32), body)` writes the same bytes on any `Write` impl
+/// by measuring `body` on a `Counter` first, at the cost of applying it twice. If `body`
+/// writes more than `u32::MAX` bytes, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{length_value, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(length_value(string("abcd")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"\x00\x00\x00\x04abcd"[..]);
+/// ```
+pub fn length_value<F, W: BackToTheBuffer>(body: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<W>,
+{
+    back_to_the_buffer(
+        4,
+        move |out: WriteContext<W>| {
+            let start = out.position;
+            let out = body(out)?;
+            let len = out.position - start;
+            Ok((out, len))
+        },
+        |out, len| {
+            if len > u64::from(u32::MAX) {
+                return Err(GenError::InvalidValue);
+            }
+            crate::bytes::be_u32(len as u32)(out)
+        },
+    )
+}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::bytes::{be_u32, be_u8};
-    use crate::sequence::tuple;
+/// Writes the output of `body` between two copies of `marker`, with its length after
+/// the first one
+///
+/// The length field takes `length_width` bytes, written by `length` once `body` was
+/// applied, like in `length_value`. The length only covers the output of `body`, not the
+/// markers or the length field. `length` must write exactly `length_width` bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::{be_u16, be_u32}, combinator::{envelope, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     envelope(be_u32(0xcafe_f00d), 2, |len| be_u16(len as u16), string("abc")),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 13);
+///   assert_eq!(buf.len(), 100 - 13);
+/// }
+///
+/// assert_eq!(&buf[..13], &b"\xca\xfe\xf0\x0d\x00\x03abc\xca\xfe\xf0\x0d"[..]);
+/// ```
+pub fn envelope<M, L, H, F, W: BackToTheBuffer>(
+    marker: M,
+    length_width: usize,
+    length: L,
+    body: F,
+) -> impl SerializeFn<W>
+where
+    M: SerializeFn<W>,
+    L: Fn(u64) -> H,
+    H: SerializeFn<W>,
+    F: SerializeFn<W>,
+{
+    let framed = back_to_the_buffer(
+        length_width,
+        move |out: WriteContext<W>| {
+            let start = out.position;
+            let out = body(out)?;
+            let len = out.position - start;
+            Ok((out, len))
+        },
+        move |out, len| length(len)(out),
+    );
 
-    #[test]
-    fn test_gen_with_length() {
-        let mut buf = [0; 8];
-        {
-            let (len_buf, buf) = buf.split_at_mut(4);
-            let (_, pos) = gen(string("test"), buf).unwrap();
-            gen(be_u32(pos as u32), len_buf).unwrap();
-        }
-        assert_eq!(
-            &buf,
-            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8]
-        );
+    move |out: WriteContext<W>| {
+        let out = marker(out)?;
+        let out = framed(out)?;
+        marker(out)
     }
+}
 
-    #[test]
-    fn test_back_to_the_buffer() {
-        let mut buf = [0; 9];
-
-        let new_buf = gen_simple(
-            tuple((
-                back_to_the_buffer(
-                    4,
-                    move |buf| gen(string("test"), buf),
-                    move |buf, len| gen_simple(be_u32(len as u32), buf),
-                ),
-                be_u8(42),
-            )),
+/// Writes the output of `body` as a COBS (Consistent Overhead Byte Stuffing) frame
+///
+/// The encoded frame contains no zero bytes and is terminated by a single `0x00`
+/// delimiter. Since the encoding of a block depends on the position of the next
+/// zero byte, `body` is first serialized into a temporary `Vec<u8>`, which is
+/// allocated on every call. `cobs_with_scratch` can reuse a buffer instead.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{cobs, slice}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(cobs(slice(&[0x11, 0x22, 0x00, 0x33][..])), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &[0x03, 0x11, 0x22, 0x02, 0x33, 0x00][..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn cobs<F, W: Write>(body: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<Vec<u8>>,
+{
+    move |out: WriteContext<W>| {
+        let mut data = gen_simple(&body, Vec::new())?;
+        cobs_write(out, &mut data)
+    }
+}
+
+/// Writes the output of `body` as a COBS frame, using `scratch` as temporary buffer
+///
+/// This works like `cobs`, but instead of allocating a new buffer on every call, `body`
+/// is written through a `CellVec` to the `Vec<u8>` stored in `scratch`, after clearing it.
+/// Its allocation is then reused by the next serializations, which avoids repeated
+/// allocations in loops, and it stays in `scratch` even if `body` fails. Outside of
+/// serializers, `gen_with_scratch` takes the scratch buffer as a `&mut Vec<u8>`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{cobs_with_scratch, slice}};
+/// use std::cell::Cell;
+///
+/// let scratch = Cell::new(Vec::new());
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(cobs_with_scratch(slice(&[0x11, 0x00][..]), &scratch), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x02, 0x11, 0x01, 0x00][..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn cobs_with_scratch<'a, F, W: Write>(
+    body: F,
+    scratch: &'a std::cell::Cell<Vec<u8>>,
+) -> impl SerializeFn<W> + 'a
+where
+    F: SerializeFn<CellVec<'a>> + 'a,
+{
+    move |out: WriteContext<W>| {
+        let mut data = scratch.take();
+        data.clear();
+        scratch.set(data);
+        gen_simple(&body, CellVec::new(scratch))?;
+
+        let mut data = scratch.take();
+        let res = cobs_write(out, &mut data);
+        scratch.set(data);
+        res
+    }
+}
+
+// Appends the COBS encoding of `buf` to it, then writes the encoded part to `out`
+#[cfg(feature = "std")]
+fn cobs_write<W: Write>(mut out: WriteContext<W>, buf: &mut Vec<u8>) -> GenResult<W> {
+    let data_len = buf.len();
+    // one code byte per 254 data bytes, plus the leading code byte and the delimiter
+    buf.reserve(data_len + data_len / 254 + 2);
+
+    let mut code_index = buf.len();
+    let mut code = 1u8;
+    buf.push(0);
+
+    for i in 0..data_len {
+        // a full block does not imply a zero, so we only start a new one
+        // if there is more data to encode
+        if code == 0xFF {
+            buf[code_index] = code;
+            code_index = buf.len();
+            code = 1;
+            buf.push(0);
+        }
+
+        let byte = buf[i];
+        if byte == 0 {
+            buf[code_index] = code;
+            code_index = buf.len();
+            code = 1;
+            buf.push(0);
+        } else {
+            buf.push(byte);
+            code += 1;
+        }
+    }
+
+    buf[code_index] = code;
+    buf.push(0);
+
+    let len = buf.len() - data_len;
+    try_write!(out, len, &buf[data_len..])
+}
+
+/// Writes a byte slice as run-length encoded `(count, byte)` pairs
+///
+/// Runs longer than `max_run` are split over multiple pairs. In the worst case,
+/// when the input contains no runs, the output is twice as long as the input.
+///
+/// Panics if `max_run` is 0.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::rle};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(rle(&b"aaaabcc"[..], 255), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &[4, b'a', 1, b'b', 2, b'c'][..]);
+/// ```
+pub fn rle<S: AsRef<[u8]>, W: Write>(data: S, max_run: u8) -> impl SerializeFn<W> {
+    assert!(max_run > 0, "rle: max_run must be at least 1");
+
+    move |mut out: WriteContext<W>| {
+        let mut data = data.as_ref();
+
+        while let Some(&byte) = data.first() {
+            let run = data
+                .iter()
+                .take(max_run as usize)
+                .take_while(|&&b| b == byte)
+                .count();
+            let len = 2;
+            out = try_write!(out, len, &[run as u8, byte][..])?;
+            data = &data[run..];
+        }
+
+        Ok(out)
+    }
+}
+
+/// Reserves a checksum field of `width` bytes, applies `body`, then fills the field with
+/// the serializer `checksum` returns for the bytes `body` wrote
+///
+/// This is meant for formats that put the checksum in a header, before the data
+/// it covers. The field is reserved with `back_to_the_buffer` and `body` runs directly on
+/// the output slice after it, so nothing is allocated and `body` is only applied once.
+/// If the checksum serializer does not write exactly `width` bytes, this fails with
+/// `GenError::BufferTooBig` or `GenError::BufferTooSmall`.
+///
+/// Panics if `body` does not return the part of the slice following what it wrote.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{with_checksum_placeholder, string}, bytes::be_u8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     with_checksum_placeholder(1, string("abc"), |data: &[u8]| {
+///       be_u8(data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)))
+///     }),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0x26, b'a', b'b', b'c'][..]);
+/// ```
+pub fn with_checksum_placeholder<'a, F, H, G>(
+    width: usize,
+    body: F,
+    checksum: H,
+) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<&'a mut [u8]>,
+    H: Fn(&[u8]) -> G,
+    G: SerializeFn<&'a mut [u8]>,
+{
+    let patched = back_to_the_buffer(
+        width,
+        move |out: WriteContext<&'a mut [u8]>| {
+            let (written, out) = split_written(&body, out, "with_checksum_placeholder")?;
+            Ok((out, checksum(written)))
+        },
+        move |out: WriteContext<&'a mut [u8]>, field: G| field(out),
+    );
+
+    move |out: WriteContext<&'a mut [u8]>| {
+        if out.write.len() < width {
+            return Err(GenError::BufferTooSmall(width - out.write.len()));
+        }
+        patched(out)
+    }
+}
+
+/// Applies `f`, then calls `g` with the bytes `f` wrote and applies the returned serializer
+///
+/// This is useful for formats where a field following some data depends on that
+/// data, like its length or a checksum. Both run directly on the output slice, no
+/// temporary buffer is needed.
+///
+/// Panics if `f` does not return the part of the slice following what it wrote.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{split_at, string}, bytes::be_u8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     split_at(string("abc"), |written: &[u8]| be_u8(written.len() as u8)),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[b'a', b'b', b'c', 3][..]);
+/// ```
+pub fn split_at<'a, F, G, H>(f: F, g: G) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<&'a mut [u8]>,
+    G: Fn(&[u8]) -> H,
+    H: SerializeFn<&'a mut [u8]>,
+{
+    move |out: WriteContext<&'a mut [u8]>| {
+        let (written, out) = split_written(&f, out, "split_at")?;
+        g(written)(out)
+    }
+}
+
+/// Writes a zlib stream: the 2 byte zlib header, the output of `deflated`, then the
+/// Adler-32 checksum of `uncompressed`
+///
+/// This does not compress anything: `deflated` must write the DEFLATE encoding of
+/// `uncompressed`, which is only used to compute the checksum of the footer, as
+/// it covers the data before compression. The header advertises a 32KiB window
+/// and the default compression level.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{slice, zlib_framed}};
+///
+/// // "a" compressed with a single fixed Huffman block
+/// let deflated = [0x4b, 0x04, 0x00];
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(zlib_framed(&b"a"[..], slice(&deflated[..])), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &[0x78, 0x9c, 0x4b, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62][..]);
+/// ```
+pub fn zlib_framed<S: AsRef<[u8]>, F, W: Write>(uncompressed: S, deflated: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<W>,
+{
+    move |mut out: WriteContext<W>| {
+        let len = 2;
+        out = try_write!(out, len, &[0x78, 0x9c][..])?;
+        let mut out = deflated(out)?;
+
+        let checksum = adler32(uncompressed.as_ref());
+        let len = 4;
+        try_write!(out, len, &checksum.to_be_bytes()[..])
+    }
+}
+
+// Computes the Adler-32 checksum of `data`, as defined in RFC 1950
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // largest number of bytes that can be summed before `b` could overflow
+    const NMAX: usize = 5552;
+
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    for chunk in data.chunks(NMAX) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+/// Order of the fields written by `trailing_crc_and_length`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FooterOrder {
+    /// The length, then the CRC-32
+    LengthFirst,
+    /// The CRC-32, then the length
+    CrcFirst,
+}
+
+/// Applies `body`, then writes the number of bytes it wrote and the CRC-32 of those bytes,
+/// both as big endian `u32`, in the `order` given
+///
+/// The CRC is the one used by zlib, gzip or PNG. The length and the CRC are computed in
+/// the same pass over the serialized body, which is read back from the output slice. If
+/// `body` writes more than `u32::MAX` bytes, this fails with `GenError::InvalidValue`.
+///
+/// Panics if `body` does not return the part of the slice following what it wrote.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{string, trailing_crc_and_length, FooterOrder}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     trailing_crc_and_length(string("123456789"), FooterOrder::LengthFirst),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 17);
+///   assert_eq!(buf.len(), 100 - 17);
+/// }
+///
+/// assert_eq!(&buf[9..17], &[0, 0, 0, 9, 0xcb, 0xf4, 0x39, 0x26][..]);
+/// ```
+pub fn trailing_crc_and_length<'a, F>(body: F, order: FooterOrder) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<&'a mut [u8]>,
+{
+    split_at(body, move |written: &[u8]| {
+        let footer = if written.len() > u32::MAX as usize {
+            None
+        } else {
+            let len = (written.len() as u32).to_be_bytes();
+            let crc = crc32(written).to_be_bytes();
+            let mut footer = [0u8; 8];
+            match order {
+                FooterOrder::LengthFirst => {
+                    footer[..4].copy_from_slice(&len);
+                    footer[4..].copy_from_slice(&crc);
+                }
+                FooterOrder::CrcFirst => {
+                    footer[..4].copy_from_slice(&crc);
+                    footer[4..].copy_from_slice(&len);
+                }
+            }
+            Some(footer)
+        };
+
+        move |mut out: WriteContext<&'a mut [u8]>| {
+            let footer = footer.ok_or(GenError::InvalidValue)?;
+            let len = footer.len();
+            try_write!(out, len, &footer[..])
+        }
+    })
+}
+
+// Computes the CRC-32 of `data`, with the reflected 0xEDB88320 polynomial of ISO 3309
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Writes the output of `pattern` repeatedly until the output slice is full
+///
+/// `pattern` is serialized once to a temporary `Vec<u8>`, then copied as many times as
+/// it fits. If the remaining space is not a multiple of the pattern length, the bytes
+/// left after the last full copy are set to 0, so the whole slice is always written.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{fill_remaining, string}};
+///
+/// let mut buf = [0xffu8; 8];
+///
+/// {
+///   let (buf, pos) = gen(fill_remaining(string("abc")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert!(buf.is_empty());
+/// }
+///
+/// assert_eq!(&buf[..], &b"abcabc\x00\x00"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn fill_remaining<'a, F>(pattern: F) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<Vec<u8>>,
+{
+    move |mut out: WriteContext<&'a mut [u8]>| {
+        let pattern = gen_simple(&pattern, Vec::new())?;
+        let len = pattern.len();
+
+        if len > 0 {
+            while out.write.len() >= len {
+                out = try_write!(out, len, &pattern[..])?;
+            }
+        }
+
+        let tail = out.write.len();
+        out.write.fill(0);
+        <&mut [u8]>::skip(out, tail)
+    }
+}
+
+/// Applies `f`, then sets the rest of the output slice to 0
+///
+/// This makes the whole slice deterministic, so the bytes following what `f` wrote do
+/// not leak what was in the buffer before. The zeroed tail counts as written: the
+/// position moves to the end of the slice and the returned slice is empty.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{string, zero_remaining}};
+///
+/// let mut buf = [0xffu8; 8];
+///
+/// {
+///   let (buf, pos) = gen(zero_remaining(string("abc")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert!(buf.is_empty());
+/// }
+///
+/// assert_eq!(&buf[..], &b"abc\x00\x00\x00\x00\x00"[..]);
+/// ```
+pub fn zero_remaining<'a, F>(f: F) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<&'a mut [u8]>,
+{
+    move |out: WriteContext<&'a mut [u8]>| {
+        let out = f(out)?;
+        let tail = out.write.len();
+        out.write.fill(0);
+        <&mut [u8]>::skip(out, tail)
+    }
+}
+
+/// Writes a header holding the total length of a list of serializers, then the
+/// serializers in sequence
+///
+/// `sized_group!(header, a, b, c)` first runs `compose!(a, b, c)` on a `Counter` to get
+/// the length of its output, then writes `header(len)` followed by `compose!(a, b, c)`.
+/// Unlike `back_to_the_buffer`, this works with any `Write` impl and variable size
+/// headers, at the cost of serializing the group twice. Since the serializer expressions
+/// are evaluated once for each pass, they should not have side effects.
+///
+/// ```rust
+/// use cookie_factory::{gen, sized_group, bytes::{be_u16, be_u8}, combinator::string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     sized_group!(|len| be_u8(len as u8), string("abcd"), be_u16(0x20)),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 7);
+///   assert_eq!(buf.len(), 100 - 7);
+/// }
+///
+/// assert_eq!(&buf[..7], &b"\x06abcd\x00\x20"[..]);
+/// ```
+#[macro_export]
+macro_rules! sized_group(
+  ($header:expr, $($f:expr),+ $(,)?) => (
+    move |out: $crate::WriteContext<_>| {
+      let len = $crate::gen_len($crate::compose!($($f),+))?;
+      let out = ($header)(len)(out)?;
+      $crate::compose!($($f),+)(out)
+    }
+  );
+);
+
+/// Writes the discriminant of the variant `value` matches, followed by its payload
+///
+/// `tagged_union!(value, tag; pattern => (discriminant, payload), ...)` matches `value`
+/// against each pattern in order, then writes `tag(discriminant)` followed by `payload`
+/// for the first one that matches. `tag` sets the width and byte order of the
+/// discriminant, like `be_u8` or `le_u16`. The patterns can bind fields used by the
+/// payload serializers. Since the serializer can be applied several times, `value` should
+/// usually be a reference.
+///
+/// ```rust
+/// use cookie_factory::{gen, tagged_union, bytes::{be_u16, be_u8}, sequence::pair};
+///
+/// enum Shape {
+///   Point,
+///   Circle(u16),
+///   Rect { w: u8, h: u8 },
+/// }
+///
+/// let mut buf = [0u8; 100];
+/// let shape = Shape::Rect { w: 3, h: 4 };
+///
+/// {
+///   let (buf, pos) = gen(
+///     tagged_union!(&shape, be_u8;
+///       Shape::Point => (0, pair(be_u8(0), be_u8(0))),
+///       Shape::Circle(r) => (1, pair(be_u16(*r), be_u8(0))),
+///       Shape::Rect { w, h } => (2, pair(be_u8(*w), be_u8(*h))),
+///     ),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[2, 3, 4][..]);
+/// ```
+#[macro_export]
+macro_rules! tagged_union(
+  ($value:expr, $tag:expr; $($pattern:pat => ($discriminant:expr, $payload:expr)),+ $(,)?) => (
+    move |out: $crate::WriteContext<_>| match $value {
+      $($pattern => ($tag)($discriminant)(out).and_then($payload),)+
+    }
+  );
+);
+
+/// Writes a byte slice, replacing the bytes for which `escaper` returns a sequence
+///
+/// Bytes for which `escaper` returns `None` are written as is, in runs as long as
+/// possible.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::escape};
+///
+/// let xml = |b: u8| match b {
+///   b'<' => Some(&b"&lt;"[..]),
+///   b'&' => Some(&b"&amp;"[..]),
+///   _ => None,
+/// };
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(escape(&b"a<b"[..], xml), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &b"a&lt;b"[..]);
+/// ```
+pub fn escape<S, E, W: Write>(data: S, escaper: E) -> impl SerializeFn<W>
+where
+    S: AsRef<[u8]>,
+    E: Fn(u8) -> Option<&'static [u8]>,
+{
+    move |mut out: WriteContext<W>| {
+        let mut data = data.as_ref();
+
+        while !data.is_empty() {
+            let next = data
+                .iter()
+                .enumerate()
+                .find_map(|(i, &b)| escaper(b).map(|replacement| (i, replacement)));
+            let (run, replacement) = match next {
+                Some((i, replacement)) => (i, Some(replacement)),
+                None => (data.len(), None),
+            };
+
+            let len = run;
+            out = try_write!(out, len, &data[..run])?;
+            data = &data[run..];
+
+            if let Some(replacement) = replacement {
+                let len = replacement.len();
+                out = try_write!(out, len, replacement)?;
+                data = &data[1..];
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Applies `f` and records the byte range it wrote under `label`
+///
+/// This only works on the `Traced` wrapper used by `gen_debug_trace`, to find out which
+/// part of a serializer produced some bytes of its output.
+///
+/// ```rust
+/// use cookie_factory::{gen_debug_trace, bytes::be_u8, combinator::named, sequence::tuple};
+///
+/// let (buf, _, trace) = gen_debug_trace(
+///   named("header", tuple((be_u8(1), named("version", be_u8(2))))),
+///   Vec::new()
+/// ).unwrap();
+///
+/// assert_eq!(buf, vec![1, 2]);
+/// assert_eq!(trace, vec![("header", 0..2), ("version", 1..2)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn named<F, W: Write>(label: &'static str, f: F) -> impl SerializeFn<Traced<W>>
+where
+    F: SerializeFn<Traced<W>>,
+{
+    move |mut out: WriteContext<Traced<W>>| {
+        let index = out.write.open(label, out.position as usize);
+        let mut out = f(out)?;
+        out.write.close(index, out.position as usize);
+        Ok(out)
+    }
+}
+
+/// Writes the `base` template with some of its ranges replaced
+///
+/// Each patch is an offset in `base` and the bytes written there instead of the
+/// template's. Patches must be sorted by offset, must not overlap and must fit in
+/// `base`, otherwise this fails with `GenError::InvalidValue` before writing anything.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::template};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     template(&b"v=?, n=??"[..], &[(2, &b"1"[..]), (7, &b"42"[..])]),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &b"v=1, n=42"[..]);
+/// ```
+pub fn template<'a, S: AsRef<[u8]> + 'a, W: Write>(
+    base: S,
+    patches: &'a [(usize, &'a [u8])],
+) -> impl SerializeFn<W> + 'a {
+    move |mut out: WriteContext<W>| {
+        let base = base.as_ref();
+
+        let mut end = 0;
+        for &(offset, data) in patches {
+            match offset.checked_add(data.len()) {
+                Some(patch_end) if offset >= end && patch_end <= base.len() => end = patch_end,
+                _ => return Err(GenError::InvalidValue),
+            }
+        }
+
+        let mut start = 0;
+        for &(offset, data) in patches {
+            let unpatched = &base[start..offset];
+            let len = unpatched.len();
+            out = try_write!(out, len, unpatched)?;
+            let len = data.len();
+            out = try_write!(out, len, data)?;
+            start = offset + data.len();
+        }
+
+        let rest = &base[start..];
+        let len = rest.len();
+        try_write!(out, len, rest)
+    }
+}
+
+/// Writes the UTF-8 byte order mark, `EF BB BF`
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::bom};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(bom(), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0xef, 0xbb, 0xbf][..]);
+/// ```
+pub fn bom<W: Write>() -> impl SerializeFn<W> {
+    slice(&[0xef, 0xbb, 0xbf][..])
+}
+
+/// Line terminator written by `line`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// `\n`, as used on Unix
+    Lf,
+    /// `\r\n`, as used on Windows and by many network protocols
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the bytes of the line terminator
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// Applies `body`, then writes the `ending` line terminator
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{line, string, LineEnding}, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     pair(line(string("a"), LineEnding::CrLf), line(string("b"), LineEnding::Lf)),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"a\r\nb\n"[..]);
+/// ```
+pub fn line<F, W: Write>(body: F, ending: LineEnding) -> impl SerializeFn<W>
+where
+    F: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| body(out).and_then(slice(ending.as_bytes()))
+}
+
+/// Applies `f` and adds the call and the number of bytes it wrote to the statistics of `label`
+///
+/// This only works on the `Profiled` wrapper used by `gen_count_writes`, to find out
+/// which serializers are applied the most in a serialization.
+///
+/// ```rust
+/// use cookie_factory::{gen_count_writes, bytes::be_u8, combinator::counted, sequence::pair};
+///
+/// let (_, _, profile) = gen_count_writes(
+///   pair(counted("byte", be_u8(1)), counted("byte", be_u8(2))),
+///   Vec::new()
+/// ).unwrap();
+///
+/// assert_eq!(profile["byte"].calls, 2);
+/// ```
+#[cfg(feature = "std")]
+pub fn counted<F, W: Write>(label: &'static str, f: F) -> impl SerializeFn<Profiled<W>>
+where
+    F: SerializeFn<Profiled<W>>,
+{
+    move |out: WriteContext<Profiled<W>>| {
+        let start = out.position;
+        let mut out = f(out)?;
+        let bytes = out.position - start;
+        out.write.record(label, bytes);
+        Ok(out)
+    }
+}
+
+/// Writes a byte slice as a series of chunks of at most `max_chunk` bytes, each prefixed
+/// by its length as a big endian `u16`, then an empty chunk marking the end
+///
+/// Panics if `max_chunk` is 0 or larger than `u16::MAX`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::chunked};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(chunked(&b"abcde"[..], 3), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 11);
+///   assert_eq!(buf.len(), 100 - 11);
+/// }
+///
+/// assert_eq!(&buf[..11], &b"\x00\x03abc\x00\x02de\x00\x00"[..]);
+/// ```
+pub fn chunked<S: AsRef<[u8]>, W: Write>(data: S, max_chunk: usize) -> impl SerializeFn<W> {
+    assert!(
+        max_chunk > 0 && max_chunk <= u16::MAX as usize,
+        "chunked: max_chunk must be between 1 and u16::MAX"
+    );
+
+    move |mut out: WriteContext<W>| {
+        for chunk in data.as_ref().chunks(max_chunk) {
+            out = crate::bytes::be_u16(chunk.len() as u16)(out)?;
+            let len = chunk.len();
+            out = try_write!(out, len, chunk)?;
+        }
+
+        crate::bytes::be_u16(0)(out)
+    }
+}
+
+/// Writes each of the already serialized `parts`, prefixed by its length as a big endian
+/// integer of `width` bytes
+///
+/// This assembles sub-objects that were serialized independently. If one part is too long
+/// for its length to fit in `width` bytes, this fails with `GenError::InvalidValue` before
+/// writing anything.
+///
+/// Panics if `width` is 0 or larger than 8.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::concat_framed};
+///
+/// let mut buf = [0u8; 100];
+/// let parts = vec![b"abc".to_vec(), b"de".to_vec()];
+///
+/// {
+///   let (buf, pos) = gen(concat_framed(&parts, 2), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &b"\x00\x03abc\x00\x02de"[..]);
+/// ```
+pub fn concat_framed<P, S, W: Write>(parts: P, width: usize) -> impl SerializeFn<W>
+where
+    P: AsRef<[S]>,
+    S: AsRef<[u8]>,
+{
+    assert!(
+        width > 0 && width <= 8,
+        "concat_framed: width must be between 1 and 8"
+    );
+
+    move |mut out: WriteContext<W>| {
+        let parts = parts.as_ref();
+        if parts
+            .iter()
+            .any(|part| width < 8 && part.as_ref().len() as u64 >> (width * 8) != 0)
+        {
+            return Err(GenError::InvalidValue);
+        }
+
+        for part in parts {
+            let part = part.as_ref();
+            let prefix = (part.len() as u64).to_be_bytes();
+            out = try_write!(out, width, &prefix[8 - width..])?;
+            let len = part.len();
+            out = try_write!(out, len, part)?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Applies `body`, then writes the number of bytes it wrote as a big endian `u32`
+///
+/// The length is computed from the write position, so no temporary buffer is needed.
+/// If `body` writes more than `u32::MAX` bytes, this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{length_suffixed, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(length_suffixed(string("abc")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 7);
+///   assert_eq!(buf.len(), 100 - 7);
+/// }
+///
+/// assert_eq!(&buf[..7], &b"abc\x00\x00\x00\x03"[..]);
+/// ```
+pub fn length_suffixed<F, W: Write>(body: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| {
+        let start = out.position;
+        let out = body(out)?;
+        let len = out.position - start;
+        if len > u64::from(u32::MAX) {
+            return Err(GenError::InvalidValue);
+        }
+
+        crate::bytes::be_u32(len as u32)(out)
+    }
+}
+
+/// Applies `body`, then writes `fill` bytes until the number of bytes written is a
+/// multiple of `block`
+///
+/// The padding depends on the length of the output of `body`, not on the position it
+/// started at, so a body written at any offset is padded the same way. Nothing is added
+/// if the length is already a multiple of `block`, including for an empty body.
+///
+/// Panics if `block` is 0.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{pad_to_multiple, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(pad_to_multiple(string("abcde"), 4, b' '), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"abcde   "[..]);
+/// ```
+pub fn pad_to_multiple<F, W: Write>(body: F, block: usize, fill: u8) -> impl SerializeFn<W>
+where
+    F: SerializeFn<W>,
+{
+    assert!(block > 0, "pad_to_multiple: block must not be 0");
+
+    move |out: WriteContext<W>| {
+        let start = out.position;
+        let mut out = body(out)?;
+        let written = out.position - start;
+
+        let padding = [fill; 64];
+        let mut remaining = ((block as u64 - written % block as u64) % block as u64) as usize;
+        while remaining > 0 {
+            let len = crate::lib::std::cmp::min(remaining, padding.len());
+            out = try_write!(out, len, &padding[..len])?;
+            remaining -= len;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Applies `f`, making it fail with `GenError::LimitExceeded` if it tries to write more
+/// than `hard_limit` bytes
+///
+/// The limit holds even if the output slice is larger, to protect the data following the
+/// region `f` is meant to write. If the output slice is smaller than `hard_limit`, running
+/// out of space is still reported as `GenError::BufferTooSmall`.
+///
+/// ```rust
+/// use cookie_factory::{gen, GenError, combinator::{bounded, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(bounded(string("abcd"), 4), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+///
+/// let res = gen(bounded(string("abcde"), 4), &mut buf[..]);
+/// assert!(matches!(res, Err(GenError::LimitExceeded(1))));
+/// ```
+pub fn bounded<'a, F>(f: F, hard_limit: usize) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<&'a mut [u8]>,
+{
+    move |out: WriteContext<&'a mut [u8]>| {
+        let ptr = out.write.as_mut_ptr();
+        let available = out.write.len();
+        let limited = hard_limit < available;
+        let limit = crate::lib::std::cmp::min(hard_limit, available);
+
+        // SAFETY: this covers the first `limit` bytes of the slice `out` wrapped, which is
+        // not used after this point, so `f` gets the only reference to them.
+        let head = WriteContext {
+            write: unsafe { crate::lib::std::slice::from_raw_parts_mut(ptr, limit) },
+            position: out.position,
+        };
+
+        let out = match f(head) {
+            Err(GenError::BufferTooSmall(n)) if limited => return Err(GenError::LimitExceeded(n)),
+            res => res?,
+        };
+
+        let len = limit.wrapping_sub(out.write.len());
+        assert!(
+            len <= limit && crate::lib::std::ptr::eq(out.write.as_ptr(), ptr.wrapping_add(len)),
+            "bounded: the serializer did not advance the output slice"
+        );
+        // SAFETY: the assert above checks that `f` returned the end of the first `limit`
+        // bytes, after the `len` bytes it wrote. That slice is dropped here and replaced by
+        // the rest of the original slice, derived from the same pointer.
+        let write =
+            unsafe { crate::lib::std::slice::from_raw_parts_mut(ptr.add(len), available - len) };
+
+        Ok(WriteContext {
+            write,
+            position: out.position,
+        })
+    }
+}
+
+/// Writes a domain name in the DNS wire format, without compression
+///
+/// Each dot separated label is written as its length on one byte followed by its bytes,
+/// and the name ends with a zero byte. A final dot is accepted, and `""` or `"."` write
+/// the root name. Empty labels, labels longer than 63 bytes and names taking more than
+/// 255 bytes once encoded fail with `GenError::InvalidValue`, before writing anything.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::dns_name};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(dns_name("www.example.com"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 17);
+///   assert_eq!(buf.len(), 100 - 17);
+/// }
+///
+/// assert_eq!(&buf[..17], &b"\x03www\x07example\x03com\x00"[..]);
+/// ```
+pub fn dns_name<S: AsRef<str>, W: Write>(name: S) -> impl SerializeFn<W> {
+    move |mut out: WriteContext<W>| {
+        let name = name.as_ref();
+        let name = name.strip_suffix('.').unwrap_or(name);
+        let labels = || name.split('.').filter(|_| !name.is_empty());
+
+        let mut encoded_len = 1;
+        for label in labels() {
+            if label.is_empty() || label.len() > 63 {
+                return Err(GenError::InvalidValue);
+            }
+            encoded_len += 1 + label.len();
+        }
+        if encoded_len > 255 {
+            return Err(GenError::InvalidValue);
+        }
+
+        for label in labels() {
+            out = crate::bytes::be_u8(label.len() as u8)(out)?;
+            let len = label.len();
+            out = try_write!(out, len, label.as_bytes())?;
+        }
+
+        crate::bytes::be_u8(0)(out)
+    }
+}
+
+/// Writes a string as XML text content, escaping `&`, `<` and `>`
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::xml_text};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(xml_text("a<b"), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &b"a&lt;b"[..]);
+/// ```
+pub fn xml_text<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        escape(data.as_ref().as_bytes(), |b| match b {
+            b'&' => Some(&b"&amp;"[..]),
+            b'<' => Some(&b"&lt;"[..]),
+            b'>' => Some(&b"&gt;"[..]),
+            _ => None,
+        })(out)
+    }
+}
+
+/// Writes a string as the value of an XML attribute, escaping `&`, `<`, `>`, `"` and `'`
+///
+/// The quotes around the value are not written.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{string, xml_attr}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     tuple((string("a=\""), xml_attr("\"b\""), string("\""))),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 17);
+///   assert_eq!(buf.len(), 100 - 17);
+/// }
+///
+/// assert_eq!(&buf[..17], &b"a=\"&quot;b&quot;\""[..]);
+/// ```
+pub fn xml_attr<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        escape(data.as_ref().as_bytes(), |b| match b {
+            b'&' => Some(&b"&amp;"[..]),
+            b'<' => Some(&b"&lt;"[..]),
+            b'>' => Some(&b"&gt;"[..]),
+            b'"' => Some(&b"&quot;"[..]),
+            b'\'' => Some(&b"&apos;"[..]),
+            _ => None,
+        })(out)
+    }
+}
+
+/// Writes a string as a CSV field, quoting it if needed as described in RFC 4180
+///
+/// A field containing a comma, a double quote, `\r` or `\n` is written between double
+/// quotes, with its double quotes doubled. Other fields are written as is.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{csv_field, string}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(tuple((csv_field("a"), string(","), csv_field("b,c"))), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 7);
+///   assert_eq!(buf.len(), 100 - 7);
+/// }
+///
+/// assert_eq!(&buf[..7], &b"a,\"b,c\""[..]);
+/// ```
+pub fn csv_field<S: AsRef<str>, W: Write>(data: S) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        let data = data.as_ref().as_bytes();
+        if !data
+            .iter()
+            .any(|b| matches!(b, b',' | b'"' | b'\r' | b'\n'))
+        {
+            return slice(data)(out);
+        }
+
+        let quoted = escape(data, |b| match b {
+            b'"' => Some(&b"\"\""[..]),
+            _ => None,
+        });
+        slice(b"\"")(out).and_then(quoted).and_then(slice(b"\""))
+    }
+}
+
+/// Builds a serializer for recursive data, like trees
+///
+/// A serializer returning `impl SerializeFn` cannot use itself for the children of a node,
+/// as its type would contain itself. `recursive` breaks that cycle by boxing: `f` gets the
+/// value to serialize and a function building the boxed serializer of any other value,
+/// used for the children, and returns the boxed serializer of the value. That function only
+/// lives during the call to `f`, so the serializers of the children have to be built
+/// eagerly, for example by collecting them in a `Vec`.
+///
+/// ```rust
+/// use cookie_factory::{gen, BoxedSerializeFn, bytes::be_u8, combinator::recursive, sequence::pair};
+/// use std::io::Write;
+///
+/// struct Node {
+///   value: u8,
+///   children: Vec<Node>,
+/// }
+///
+/// let tree = Node {
+///   value: 1,
+///   children: vec![
+///     Node { value: 2, children: vec![] },
+///     Node { value: 3, children: vec![Node { value: 4, children: vec![] }] },
+///   ],
+/// };
+///
+/// fn node<'a, W: Write + 'a>() -> impl Fn(&'a Node) -> BoxedSerializeFn<'a, W> {
+///   recursive(|node, n: &'a Node| {
+///     let children: Vec<_> = n.children.iter().map(node).collect();
+///     Box::new(pair(be_u8(n.value), move |out| children.iter().try_fold(out, |out, child| child(out))))
+///   })
+/// }
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(node()(&tree), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[1u8, 2u8, 3u8, 4u8][..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn recursive<'a, I, W, F>(f: F) -> impl Fn(I) -> BoxedSerializeFn<'a, W>
+where
+    F: Fn(&dyn Fn(I) -> BoxedSerializeFn<'a, W>, I) -> BoxedSerializeFn<'a, W>,
+{
+    move |i| recursive_apply(&f, i)
+}
+
+// Calls `f` with a function calling `recursive_apply` again for the children
+#[cfg(feature = "std")]
+fn recursive_apply<'a, I, W, F>(f: &F, i: I) -> BoxedSerializeFn<'a, W>
+where
+    F: Fn(&dyn Fn(I) -> BoxedSerializeFn<'a, W>, I) -> BoxedSerializeFn<'a, W>,
+{
+    f(&|i| recursive_apply(f, i), i)
+}
+
+/// Applies `f`, then XORs the bytes it wrote with `key`, repeated as needed
+///
+/// The masked region starts at the first byte written by `f`, so the key is aligned on
+/// it whatever the position. An empty key leaves the output unchanged.
+///
+/// Panics if `f` does not return the part of the slice following what it wrote.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{string, xor_mask}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(xor_mask(string("abc"), &[0x20, 0x00][..]), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &b"AbC"[..]);
+/// ```
+pub fn xor_mask<'a, 'k, F>(f: F, key: &'k [u8]) -> impl SerializeFn<&'a mut [u8]> + 'k
+where
+    'a: 'k,
+    F: SerializeFn<&'a mut [u8]> + 'k,
+{
+    move |out: WriteContext<&'a mut [u8]>| {
+        let (written, out) = split_written(&f, out, "xor_mask")?;
+        for (byte, k) in written.iter_mut().zip(key.iter().cycle()) {
+            *byte ^= k;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Applies `f` and returns `value` along with the updated output
+///
+/// This turns a serializer into one returning a value, see `SerializeFnV`.
+///
+/// ```rust
+/// use cookie_factory::{GenError, WriteContext, combinator::{string, with_value}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// let (out, value) = with_value(string("abcd"), 42)(WriteContext::from(&mut buf[..]))?;
+/// assert_eq!(out.position, 4);
+/// assert_eq!(value, 42);
+/// # Ok::<(), GenError>(())
+/// ```
+pub fn with_value<F, T: Clone, W: Write>(f: F, value: T) -> impl SerializeFnV<W, T>
+where
+    F: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| Ok((f(out)?, value.clone()))
+}
+
+/// Applies `f`, ignoring the value it returns
+///
+/// This turns a value returning serializer back into a regular one.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{offset, string, without_value}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(without_value(offset(string("abcd"))), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// ```
+pub fn without_value<F, T, W: Write>(f: F) -> impl SerializeFn<W>
+where
+    F: SerializeFnV<W, T>,
+{
+    move |out: WriteContext<W>| f(out).map(|(out, _)| out)
+}
+
+/// Applies `f`, then applies the serializer `g` returns from the value `f` returned
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u8, combinator::{and_then, offset, string}, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     pair(string("ab"), and_then(offset(string("cd")), |start| be_u8(start as u8))),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"abcd\x02"[..]);
+/// ```
+pub fn and_then<F, G, H, T, W: Write>(f: F, g: G) -> impl SerializeFn<W>
+where
+    F: SerializeFnV<W, T>,
+    G: Fn(T) -> H,
+    H: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| {
+        let (out, value) = f(out)?;
+        g(value)(out)
+    }
+}
+
+/// Applies `f` and returns the position where it started writing
+///
+/// ```rust
+/// use cookie_factory::{GenError, WriteContext, combinator::{offset, string}};
+///
+/// let mut buf = [0u8; 100];
+///
+/// let out = string("ab")(WriteContext::from(&mut buf[..]))?;
+/// let (out, start) = offset(string("cd"))(out)?;
+/// assert_eq!(start, 2);
+/// assert_eq!(out.position, 4);
+/// # Ok::<(), GenError>(())
+/// ```
+pub fn offset<F, W: Write>(f: F) -> impl SerializeFnV<W, u64>
+where
+    F: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| {
+        let start = out.position;
+        Ok((f(out)?, start))
+    }
+}
+
+/// Applies the first serializer of `candidates` that fits in the output slice
+///
+/// Each candidate starts writing at the current position, so the output only contains
+/// the bytes of the chosen one: if previous candidates ran out of space after writing part
+/// of their data, the rest of the slice after the chosen candidate's output is zeroed.
+/// This is useful to write a value in the most compact form that fits, with candidates
+/// sorted from the most to the least preferred.
+///
+/// Running out of space moves on to the next candidate, while other errors are returned
+/// immediately. If no candidate fits, this returns the `GenError::BufferTooSmall` of the
+/// last one, and the slice keeps whatever the candidates wrote. It fails with
+/// `GenError::InvalidValue` if there are no candidates.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{alt, string}};
+///
+/// let mut buf = [0u8; 4];
+///
+/// {
+///   let (buf, pos) = gen(alt(["abcdef", "abcd", "ab"].iter().map(string)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 0);
+/// }
+///
+/// assert_eq!(&buf[..], &b"abcd"[..]);
+/// ```
+pub fn alt<'a, G, It>(candidates: It) -> impl SerializeFn<&'a mut [u8]>
+where
+    G: SerializeFn<&'a mut [u8]>,
+    It: Clone + Iterator<Item = G>,
+{
+    move |out: WriteContext<&'a mut [u8]>| {
+        let start = out.write.as_mut_ptr();
+        let len = out.write.len();
+        let position = out.position;
+        let mut res = Err(GenError::InvalidValue);
+        let mut dirty = false;
+
+        for candidate in candidates.clone() {
+            // SAFETY: this is the slice `out` wrapped, and a new one is only created once
+            // the previous one was dropped along with the candidate's error, so there is
+            // never more than one live reference to it.
+            let write = unsafe { crate::lib::std::slice::from_raw_parts_mut(start, len) };
+            res = candidate(WriteContext { write, position });
+            match res {
+                Err(GenError::BufferTooSmall(_)) => dirty = true,
+                Ok(ctx) => {
+                    // failed candidates may have written anywhere up to the end of the slice
+                    if dirty {
+                        for b in ctx.write.iter_mut() {
+                            *b = 0;
+                        }
+                    }
+                    return Ok(ctx);
+                }
+                Err(_) => return res,
+            }
+        }
+
+        res
+    }
+}
+
+/// Applies `f` to a temporary buffer, then copies its output to the output slice only if
+/// `f` succeeded and its output fits
+///
+/// On any error the output slice is left untouched, where other serializers may have
+/// written part of their data before running out of space. `f` sees the same position
+/// as if it was writing to the output slice directly.
+///
+/// ```rust
+/// use cookie_factory::{gen, GenError, combinator::{atomic, string}, sequence::pair};
+///
+/// let mut buf = [0u8; 4];
+///
+/// let res = gen(atomic(pair(string("ab"), string("cde"))), &mut buf[..]);
+/// assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+/// assert_eq!(&buf[..], &[0u8; 4][..]);
+///
+/// {
+///   let (buf, pos) = gen(atomic(string("abcd")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 0);
+/// }
+///
+/// assert_eq!(&buf[..], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn atomic<'a, F>(f: F) -> impl SerializeFn<&'a mut [u8]>
+where
+    F: SerializeFn<Vec<u8>>,
+{
+    move |mut out: WriteContext<&'a mut [u8]>| {
+        let scratch = f(WriteContext {
+            write: Vec::new(),
+            position: out.position,
+        })?;
+        let data = scratch.write;
+        let len = data.len();
+        if len > out.write.len() {
+            return Err(GenError::BufferTooSmall(len - out.write.len()));
+        }
+
+        try_write!(out, len, &data[..])
+    }
+}
+
+/// Reads exactly `len` bytes from `reader` and writes them to the output
+///
+/// The data is copied through a small buffer on the stack. Since reading consumes the
+/// data, applying the serializer again continues from where the previous run stopped.
+/// If the reader ends before `len` bytes, this fails with a `GenError::IoError` of kind
+/// `UnexpectedEof`.
+///
+/// The bytes read before an error are lost, including when the output runs out of space,
+/// so retrying with a bigger buffer does not write the same data. For output slices,
+/// `copy_reader_checked` checks the space first and reads nothing if it is too small.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::copy_reader};
+/// use std::io::Cursor;
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(copy_reader(Cursor::new(b"abcdef"), 4), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn copy_reader<R: std::io::Read, W: Write>(reader: R, len: usize) -> impl SerializeFn<W> {
+    let reader = std::cell::RefCell::new(reader);
+
+    move |mut out: WriteContext<W>| {
+        let mut reader = reader.borrow_mut();
+        let mut chunk = [0u8; 512];
+        let mut remaining = len;
+
+        while remaining > 0 {
+            let n = crate::lib::std::cmp::min(remaining, chunk.len());
+            reader.read_exact(&mut chunk[..n])?;
+            match out.write(&chunk[..n]) {
+                Err(io) => return Err(GenError::IoError(io)),
+                Ok(written) if written < n => {
+                    return Err(GenError::BufferTooSmall(remaining - written))
+                }
+                Ok(_) => remaining -= n,
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Reads exactly `len` bytes from `reader` straight into the output slice
+///
+/// This works like `copy_reader`, but fails with `GenError::BufferTooSmall` before
+/// reading anything if the output slice is shorter than `len`, so the reader is still
+/// at the same place when retrying with a bigger buffer.
+///
+/// ```rust
+/// use cookie_factory::{gen, GenError, combinator::copy_reader_checked};
+/// use std::io::Cursor;
+///
+/// let mut reader = Cursor::new(b"abcdef");
+///
+/// let mut small = [0u8; 2];
+/// let res = gen(copy_reader_checked(&mut reader, 4), &mut small[..]);
+/// assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(copy_reader_checked(&mut reader, 4), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn copy_reader_checked<'a, R: std::io::Read>(
+    reader: R,
+    len: usize,
+) -> impl SerializeFn<&'a mut [u8]> {
+    let reader = std::cell::RefCell::new(reader);
+
+    move |out: WriteContext<&'a mut [u8]>| {
+        if out.write.len() < len {
+            return Err(GenError::BufferTooSmall(len - out.write.len()));
+        }
+
+        let (data, rest) = out.write.split_at_mut(len);
+        reader.borrow_mut().read_exact(data)?;
+        Ok(WriteContext {
+            write: rest,
+            position: out.position + len as u64,
+        })
+    }
+}
+
+/// Writes `data` encoded in base64, with the standard alphabet and padding
+///
+/// The output always takes `base64_max_size(data.len())` bytes.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::base64};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(base64(&b"hello"[..]), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"aGVsbG8="[..]);
+/// ```
+pub fn base64<S: AsRef<[u8]>, W: Write>(data: S) -> impl SerializeFn<W> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    move |mut out: WriteContext<W>| {
+        let data = data.as_ref();
+        let mut encoded = [0u8; 64];
+        let mut remaining = base64_max_size(data.len());
+
+        for part in data.chunks(48) {
+            let mut len = 0;
+            for group in part.chunks(3) {
+                let b = [
+                    group[0],
+                    *group.get(1).unwrap_or(&0),
+                    *group.get(2).unwrap_or(&0),
+                ];
+                let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+                for (i, c) in encoded[len..len + 4].iter_mut().enumerate() {
+                    *c = if i <= group.len() {
+                        ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]
+                    } else {
+                        b'='
+                    };
+                }
+                len += 4;
+            }
+
+            out = match out.write(&encoded[..len]) {
+                Err(io) => return Err(GenError::IoError(io)),
+                Ok(n) if n < len => return Err(GenError::BufferTooSmall(remaining - n)),
+                Ok(_) => out,
+            };
+            remaining -= len;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Returns the number of bytes written by `base64` for `len` bytes of input
+///
+/// ```rust
+/// use cookie_factory::combinator::base64_max_size;
+///
+/// assert_eq!(base64_max_size(10), 16);
+/// ```
+pub fn base64_max_size(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Wrapper for data that is serialized in base64, like with `base64`
+///
+/// Its `MaxSize` is `base64_max_size` of the data length.
+///
+/// ```rust
+/// use cookie_factory::{gen, MaxSize, combinator::Base64};
+///
+/// let data = Base64(b"hello");
+/// let mut buf = [0u8; 8];
+///
+/// assert_eq!(MaxSize::<&mut [u8]>::max_size(&data), 8);
+/// gen(|w| data.serialize(w), &mut buf[..]).unwrap();
+///
+/// assert_eq!(&buf[..], &b"aGVsbG8="[..]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Base64<S>(pub S);
+
+impl<W: Write, S: AsRef<[u8]>> MaxSize<W> for Base64<S> {
+    fn max_size(&self) -> usize {
+        base64_max_size(self.0.as_ref().len())
+    }
+
+    fn serialize(&self, w: WriteContext<W>) -> GenResult<W> {
+        base64(self.0.as_ref())(w)
+    }
+}
+
+/// Writes a text hex dump of the output of `f`, with the offset of each line
+///
+/// This is meant for debugging: `f` is serialized to a temporary `Vec<u8>`, then each
+/// group of 16 bytes is written as a line starting with its offset from the start of the
+/// output of `f`, in 8 hexadecimal digits, followed by the bytes in hexadecimal.
+///
+/// ```rust
+/// use cookie_factory::{gen_simple, combinator::{string, with_offset_comments}};
+///
+/// let dump = gen_simple(with_offset_comments(string("cookie factory dump")), Vec::new()).unwrap();
+///
+/// assert_eq!(
+///   String::from_utf8(dump).unwrap(),
+///   "00000000: 63 6f 6f 6b 69 65 20 66 61 63 74 6f 72 79 20 64\n\
+///    00000010: 75 6d 70\n"
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn with_offset_comments<F, W: Write>(f: F) -> impl SerializeFn<W>
+where
+    F: SerializeFn<Vec<u8>>,
+{
+    move |mut out: WriteContext<W>| {
+        use crate::lib::std::fmt::Write as _;
+
+        let data = gen_simple(&f, Vec::new())?;
+        let mut text = String::new();
+
+        for (i, line) in data.chunks(16).enumerate() {
+            text.clear();
+            // writing to a `String` cannot fail
+            let _ = write!(text, "{:08x}:", i * 16);
+            for byte in line {
+                let _ = write!(text, " {:02x}", byte);
+            }
+            text.push('\n');
+
+            let len = text.len();
+            out = try_write!(out, len, text.as_bytes())?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Writes the time returned by `clock` as a big endian `u64` number of seconds since the
+/// Unix epoch
+///
+/// The clock is called each time the serializer is applied. Pass `SystemTime::now` to
+/// write the current time, or a function returning a fixed time to get reproducible
+/// output, in tests or reproducible builds. Times before the epoch fail with
+/// `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::unix_timestamp_be_u64};
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// let mut buf = [0u8; 100];
+/// let clock = || UNIX_EPOCH + Duration::from_secs(0x0102_0304);
+///
+/// {
+///   let (buf, pos) = gen(unix_timestamp_be_u64(clock), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0, 0, 0, 0, 1, 2, 3, 4][..]);
+///
+/// gen(unix_timestamp_be_u64(SystemTime::now), &mut buf[..]).unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub fn unix_timestamp_be_u64<C, W: Write>(clock: C) -> impl SerializeFn<W>
+where
+    C: Fn() -> std::time::SystemTime,
+{
+    move |out: WriteContext<W>| {
+        let since_epoch = clock()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| GenError::InvalidValue)?;
+        crate::bytes::be_u64(since_epoch.as_secs())(out)
+    }
+}
+
+/// Writes a duration as a number of milliseconds, as a big endian `u64`
+///
+/// The duration is truncated to a whole number of milliseconds. If that number does not fit
+/// in an `u64`, this fails with `GenError::InvalidValue` rather than saturating.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::duration_millis_be_u64};
+/// use std::time::Duration;
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(duration_millis_be_u64(Duration::from_secs(2)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0, 0, 0, 0, 0, 0, 0x07, 0xd0][..]);
+/// ```
+pub fn duration_millis_be_u64<W: Write>(
+    duration: crate::lib::std::time::Duration,
+) -> impl SerializeFn<W> {
+    u128_as_be_u64(duration.as_millis())
+}
+
+/// Writes a duration as a number of microseconds, as a big endian `u64`
+///
+/// The duration is truncated to a whole number of microseconds. If that number does not fit
+/// in an `u64`, this fails with `GenError::InvalidValue` rather than saturating.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::duration_micros_be_u64};
+/// use std::time::Duration;
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(duration_micros_be_u64(Duration::from_millis(1)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0, 0, 0, 0, 0, 0, 0x03, 0xe8][..]);
+/// ```
+pub fn duration_micros_be_u64<W: Write>(
+    duration: crate::lib::std::time::Duration,
+) -> impl SerializeFn<W> {
+    u128_as_be_u64(duration.as_micros())
+}
+
+/// Writes a duration as a number of nanoseconds, as a big endian `u64`
+///
+/// The duration is truncated to a whole number of nanoseconds. If that number does not fit
+/// in an `u64`, this fails with `GenError::InvalidValue` rather than saturating.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::duration_nanos_be_u64};
+/// use std::time::Duration;
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(duration_nanos_be_u64(Duration::from_micros(1)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0, 0, 0, 0, 0, 0, 0x03, 0xe8][..]);
+/// ```
+pub fn duration_nanos_be_u64<W: Write>(
+    duration: crate::lib::std::time::Duration,
+) -> impl SerializeFn<W> {
+    u128_as_be_u64(duration.as_nanos())
+}
+
+// Writes `value` as a big endian `u64`, failing if it does not fit
+fn u128_as_be_u64<W: Write>(value: u128) -> impl SerializeFn<W> {
+    move |out: WriteContext<W>| {
+        if value > u128::from(u64::MAX) {
+            return Err(GenError::InvalidValue);
+        }
+        crate::bytes::be_u64(value as u64)(out)
+    }
+}
+
+/// Builds a serializer factory taking a `&T`, which projects the value with `project`
+/// and passes the result to the serializer factory `f`
+///
+/// This only changes the value type: the returned factory builds the same serializers as
+/// `f`, so they work on the same output types. It is useful to serialize a field of a
+/// struct with an existing factory like `be_u32`, without writing a closure around it.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u16, combinator::map_input};
+///
+/// struct Header {
+///   version: u16,
+/// }
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let version = map_input(|h: &Header| h.version, be_u16);
+///   let (buf, pos) = gen(version(&Header { version: 3 }), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 2);
+///   assert_eq!(buf.len(), 100 - 2);
+/// }
+///
+/// assert_eq!(&buf[..2], &[0, 3][..]);
+/// ```
+pub fn map_input<T: ?Sized, U, P, M, F, W: Write>(project: P, f: M) -> impl Fn(&T) -> F
+where
+    P: Fn(&T) -> U,
+    M: Fn(U) -> F,
+    F: SerializeFn<W>,
+{
+    move |value: &T| f(project(value))
+}
+
+/// Entries written by `dictionary_entry`, with the position where each one starts
+///
+/// The same dictionary is shared by all the `dictionary_entry` serializers of an output,
+/// and only lives as long as that output: positions are not valid in another one.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    entries: std::cell::RefCell<std::collections::HashMap<Vec<u8>, u64>>,
+}
+
+#[cfg(feature = "std")]
+impl Dictionary {
+    /// Creates an empty dictionary
+    pub fn new() -> Dictionary {
+        Dictionary::default()
+    }
+
+    /// Returns the number of distinct entries written
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Returns true if no entry was written
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Writes `data` with the serializer `new` returns, or if an identical entry was already
+/// written with `dictionary`, writes a reference to its position with the serializer
+/// `reference` returns
+///
+/// `dictionary` is usually a `&Dictionary`, shared by all the entries of the output.
+/// An entry is only added to it once it is successfully written.
+///
+/// ```rust
+/// use cookie_factory::{gen, bytes::be_u8, combinator::{dictionary_entry, slice, Dictionary}, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+/// let dictionary = Dictionary::new();
+///
+/// {
+///   let entry = |data: &'static [u8]| dictionary_entry(
+///     &dictionary,
+///     data,
+///     |data: &[u8]| pair(be_u8(data.len() as u8), slice(data.to_vec())),
+///     |position| pair(be_u8(0xff), be_u8(position as u8)),
+///   );
+///   let (buf, pos) = gen(pair(entry(b"ab"), entry(b"ab")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 5);
+///   assert_eq!(buf.len(), 100 - 5);
+/// }
+///
+/// assert_eq!(&buf[..5], &b"\x02ab\xff\x00"[..]);
+/// assert_eq!(dictionary.len(), 1);
+/// ```
+#[cfg(feature = "std")]
+pub fn dictionary_entry<D, S, N, F, R, G, W: Write>(
+    dictionary: D,
+    data: S,
+    new: N,
+    reference: R,
+) -> impl SerializeFn<W>
+where
+    D: std::borrow::Borrow<Dictionary>,
+    S: AsRef<[u8]>,
+    N: Fn(&[u8]) -> F,
+    F: SerializeFn<W>,
+    R: Fn(u64) -> G,
+    G: SerializeFn<W>,
+{
+    move |out: WriteContext<W>| {
+        let data = data.as_ref();
+        let entries = &dictionary.borrow().entries;
+
+        let known = entries.borrow().get(data).copied();
+        if let Some(position) = known {
+            return reference(position)(out);
+        }
+
+        let position = out.position;
+        let out = new(data)(out)?;
+        entries.borrow_mut().insert(data.to_vec(), position);
+
+        Ok(out)
+    }
+}
+
+/// CRC-32 of the regions written by `checksum_region`, by label
+///
+/// The same registry is shared by the `checksum_region` and `region_crc32` serializers of
+/// an output, so that a checksum can be written anywhere after the region it covers.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Regions {
+    checksums: std::cell::RefCell<std::collections::HashMap<std::borrow::Cow<'static, str>, u32>>,
+}
+
+#[cfg(feature = "std")]
+impl Regions {
+    /// Creates an empty registry
+    pub fn new() -> Regions {
+        Regions::default()
+    }
+
+    /// Returns the CRC-32 of the region written with `label`, if there is one
+    pub fn crc32(&self, label: &str) -> Option<u32> {
+        self.checksums.borrow().get(label).copied()
+    }
+}
+
+/// Applies `body` and records the CRC-32 of the bytes it wrote in `regions` under `label`
+///
+/// `regions` is usually a `&Regions`, shared with the `region_crc32` serializers writing
+/// the checksums. `body` is serialized into a temporary `Vec<u8>` to compute the CRC, then
+/// copied to the output. Writing a region again with the same label replaces its CRC.
+/// The label can be a `&'static str` or a `String` built at runtime.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{checksum_region, region_crc32, string, Regions}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+/// let regions = Regions::new();
+///
+/// {
+///   let (buf, pos) = gen(
+///     tuple((
+///       checksum_region(&regions, "text", string("123456789")),
+///       checksum_region(&regions, "data", string("a")),
+///       region_crc32(&regions, "text"),
+///     )),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 14);
+///   assert_eq!(buf.len(), 100 - 14);
+/// }
+///
+/// assert_eq!(&buf[10..14], &[0xcb, 0xf4, 0x39, 0x26][..]);
+/// assert_eq!(regions.crc32("data"), Some(0xe8b7_be43));
+/// ```
+#[cfg(feature = "std")]
+pub fn checksum_region<R, L, F, W: Write>(regions: R, label: L, body: F) -> impl SerializeFn<W>
+where
+    R: std::borrow::Borrow<Regions>,
+    L: Into<std::borrow::Cow<'static, str>>,
+    F: SerializeFn<Vec<u8>>,
+{
+    let label = label.into();
+
+    move |mut out: WriteContext<W>| {
+        let data = gen_simple(&body, Vec::new())?;
+        let len = data.len();
+        out = try_write!(out, len, &data[..])?;
+
+        regions
+            .borrow()
+            .checksums
+            .borrow_mut()
+            .insert(label.clone(), crc32(&data));
+        Ok(out)
+    }
+}
+
+/// Writes the CRC-32 of the region written with `label`, as a big endian `u32`
+///
+/// The region must have been written before, by a `checksum_region` sharing the same
+/// `regions`, otherwise this fails with `GenError::InvalidValue`.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{checksum_region, region_crc32, string, Regions}, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+/// let regions = Regions::new();
+/// let label = format!("section{}", 1);
+///
+/// {
+///   let (buf, pos) = gen(
+///     pair(
+///       checksum_region(&regions, label.clone(), string("123456789")),
+///       region_crc32(&regions, label),
+///     ),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 13);
+///   assert_eq!(buf.len(), 100 - 13);
+/// }
+///
+/// assert_eq!(&buf[9..13], &[0xcb, 0xf4, 0x39, 0x26][..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn region_crc32<R, L, W: Write>(regions: R, label: L) -> impl SerializeFn<W>
+where
+    R: std::borrow::Borrow<Regions>,
+    L: AsRef<str>,
+{
+    move |out: WriteContext<W>| {
+        let crc = regions
+            .borrow()
+            .crc32(label.as_ref())
+            .ok_or(GenError::InvalidValue)?;
+        crate::bytes::be_u32(crc)(out)
+    }
+}
+
+/// Pointer fields written by `pointer_be_u32` and the targets they point to, to patch
+/// once the whole output is written
+///
+/// The pointers and targets of an output share the same registry, which `resolve`, or
+/// `gen_with_backrefs`, then uses to write the position of each target in the pointer
+/// fields. Since positions are recorded each time the serializers are applied, they should
+/// only be applied once per output.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Pointers {
+    fields: std::cell::RefCell<Vec<(u64, &'static str)>>,
+    targets: std::cell::RefCell<std::collections::HashMap<&'static str, u64>>,
+}
+
+#[cfg(feature = "std")]
+impl Pointers {
+    /// Creates an empty registry
+    pub fn new() -> Pointers {
+        Pointers::default()
+    }
+
+    /// Writes the position of its target in each pointer field of `buf`, as a big endian
+    /// `u32`
+    ///
+    /// `buf` must be the output the pointers and targets were written to. If a target was
+    /// never written, its position does not fit in an `u32`, or a field is out of `buf`,
+    /// this fails with `GenError::InvalidValue`.
+    pub fn resolve(&self, buf: &mut [u8]) -> Result<(), GenError> {
+        let targets = self.targets.borrow();
+
+        for &(position, label) in self.fields.borrow().iter() {
+            let target = *targets.get(label).ok_or(GenError::InvalidValue)?;
+            if target > u64::from(u32::MAX) {
+                return Err(GenError::InvalidValue);
+            }
+
+            let start = position as usize;
+            buf.get_mut(start..start + 4)
+                .ok_or(GenError::InvalidValue)?
+                .copy_from_slice(&(target as u32).to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a placeholder for a big endian `u32` holding the position of `label`, and records
+/// it in `pointers`
+///
+/// The target can be written before or after the pointer, with `pointer_target`. The
+/// placeholder is 0 until `Pointers::resolve` patches it.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::{pointer_be_u32, pointer_target, string, Pointers}, sequence::tuple};
+///
+/// let mut buf = [0u8; 100];
+/// let pointers = Pointers::new();
+///
+/// {
+///   let (buf, pos) = gen(
+///     tuple((pointer_be_u32(&pointers, "name"), string("ab"), pointer_target(&pointers, "name"), string("cd"))),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// pointers.resolve(&mut buf[..]).unwrap();
+/// assert_eq!(&buf[..8], &b"\x00\x00\x00\x06abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn pointer_be_u32<P, W: Write>(pointers: P, label: &'static str) -> impl SerializeFn<W>
+where
+    P: std::borrow::Borrow<Pointers>,
+{
+    move |out: WriteContext<W>| {
+        let position = out.position;
+        let out = crate::bytes::be_u32(0)(out)?;
+        pointers
+            .borrow()
+            .fields
+            .borrow_mut()
+            .push((position, label));
+        Ok(out)
+    }
+}
+
+/// Records the current position in `pointers` as the target `label`, without writing
+/// anything
+///
+/// See `pointer_be_u32`.
+#[cfg(feature = "std")]
+pub fn pointer_target<P, W: Write>(pointers: P, label: &'static str) -> impl SerializeFn<W>
+where
+    P: std::borrow::Borrow<Pointers>,
+{
+    move |out: WriteContext<W>| {
+        pointers
+            .borrow()
+            .targets
+            .borrow_mut()
+            .insert(label, out.position);
+        Ok(out)
+    }
+}
+
+//missing combinators:
+//or
+//empty
+//then
+//stream
+//text print
+//text upperhex
+//text lowerhex
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bytes::{be_u16, be_u32, be_u8};
+    use crate::sequence::tuple;
+
+    #[test]
+    fn test_gen_with_length() {
+        let mut buf = [0; 8];
+        {
+            let (len_buf, buf) = buf.split_at_mut(4);
+            let (_, pos) = gen(string("test"), buf).unwrap();
+            gen(be_u32(pos as u32), len_buf).unwrap();
+        }
+        assert_eq!(
+            &buf,
+            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8]
+        );
+    }
+
+    #[test]
+    fn test_back_to_the_buffer() {
+        let mut buf = [0; 9];
+
+        let new_buf = gen_simple(
+            tuple((
+                back_to_the_buffer(
+                    4,
+                    move |buf| gen(string("test"), buf),
+                    move |buf, len| gen_simple(be_u32(len as u32), buf),
+                ),
+                be_u8(42),
+            )),
+            &mut buf[..],
+        )
+        .unwrap();
+
+        assert!(new_buf.is_empty());
+        assert_eq!(
+            &buf,
+            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_back_to_the_buffer_vec() {
+        let buf = Vec::new();
+
+        let buf = gen_simple(
+            tuple((
+                back_to_the_buffer(
+                    4,
+                    move |buf| gen(string("test"), buf),
+                    move |buf, len| gen_simple(be_u32(len as u32), buf),
+                ),
+                be_u8(42),
+            )),
+            buf,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &buf[..],
+            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+        );
+    }
+
+    #[test]
+    fn test_back_to_the_buffer_cursor() {
+        let mut buf = [0; 9];
+        {
+            let cursor = crate::lib::std::io::Cursor::new(&mut buf[..]);
+            let cursor = gen_simple(
+                tuple((
+                    back_to_the_buffer(
+                        4,
+                        move |buf| gen(string("test"), buf),
+                        move |buf, len| gen_simple(be_u32(len as u32), buf),
+                    ),
+                    be_u8(42),
+                )),
+                cursor,
+            )
+            .unwrap();
+            assert_eq!(cursor.position(), 9);
+        }
+        assert_eq!(
+            &buf,
+            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+        );
+    }
+
+    #[test]
+    fn test_back_to_the_buffer_cursor_counter() {
+        let mut buf = [0; 10];
+        {
+            let cursor = crate::lib::std::io::Cursor::new(&mut buf[..]);
+            let (cursor, pos) = gen(
+                tuple((
+                    be_u8(64),
+                    back_to_the_buffer(
+                        4,
+                        &move |buf| gen(string("test"), buf),
+                        &move |buf, len| gen_simple(be_u32(len as u32), buf),
+                    ),
+                    be_u8(42),
+                )),
+                cursor,
+            )
+            .unwrap();
+            assert_eq!(pos, 10);
+            assert_eq!(cursor.position(), 10);
+        }
+        assert_eq!(
+            &buf,
+            &[64, 0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cobs_vectors() {
+        fn encode(data: &[u8]) -> Vec<u8> {
+            gen_simple(cobs(slice(data)), Vec::new()).unwrap()
+        }
+
+        assert_eq!(encode(&[0x00]), vec![0x01, 0x01, 0x00]);
+        assert_eq!(encode(&[0x00, 0x00]), vec![0x01, 0x01, 0x01, 0x00]);
+        assert_eq!(
+            encode(&[0x00, 0x11, 0x00]),
+            vec![0x01, 0x02, 0x11, 0x01, 0x00]
+        );
+        assert_eq!(
+            encode(&[0x11, 0x22, 0x00, 0x33]),
+            vec![0x03, 0x11, 0x22, 0x02, 0x33, 0x00]
+        );
+        assert_eq!(
+            encode(&[0x11, 0x22, 0x33, 0x44]),
+            vec![0x05, 0x11, 0x22, 0x33, 0x44, 0x00]
+        );
+        assert_eq!(
+            encode(&[0x11, 0x00, 0x00, 0x00]),
+            vec![0x02, 0x11, 0x01, 0x01, 0x01, 0x00]
+        );
+
+        // 01 02 ... FE
+        let data: Vec<u8> = (0x01..=0xFE).collect();
+        let mut expected = vec![0xFF];
+        expected.extend(&data);
+        expected.push(0x00);
+        assert_eq!(encode(&data), expected);
+
+        // 00 01 02 ... FE
+        let mut data = vec![0x00];
+        data.extend(0x01..=0xFE);
+        let mut expected = vec![0x01, 0xFF];
+        expected.extend(0x01..=0xFE);
+        expected.push(0x00);
+        assert_eq!(encode(&data), expected);
+
+        // 01 02 ... FE FF
+        let data: Vec<u8> = (0x01..=0xFF).collect();
+        let mut expected = vec![0xFF];
+        expected.extend(0x01..=0xFE);
+        expected.extend(&[0x02, 0xFF, 0x00]);
+        assert_eq!(encode(&data), expected);
+
+        // 02 03 ... FF 00
+        let mut data: Vec<u8> = (0x02..=0xFF).collect();
+        data.push(0x00);
+        let mut expected = vec![0xFF];
+        expected.extend(0x02..=0xFF);
+        expected.extend(&[0x01, 0x01, 0x00]);
+        assert_eq!(encode(&data), expected);
+
+        // 03 04 ... FF 00 01
+        let mut data: Vec<u8> = (0x03..=0xFF).collect();
+        data.extend(&[0x00, 0x01]);
+        let mut expected = vec![0xFE];
+        expected.extend(0x03..=0xFF);
+        expected.extend(&[0x02, 0x01, 0x00]);
+        assert_eq!(encode(&data), expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cobs_buffer_too_small() {
+        let mut buf = [0u8; 4];
+        let res = gen(cobs(slice(&[0x11, 0x22, 0x33, 0x44][..])), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cobs_with_scratch_reuses_allocation() {
+        use crate::sequence::pair;
+
+        let scratch = std::cell::Cell::new(Vec::new());
+        let data = &[0x11, 0x22, 0x00, 0x33][..];
+
+        let first = gen_simple(cobs_with_scratch(slice(data), &scratch), Vec::new()).unwrap();
+        let buffer = scratch.take();
+        let capacity = buffer.capacity();
+        let pointer = buffer.as_ptr();
+        assert!(capacity > 0);
+        scratch.set(buffer);
+
+        let second = gen_simple(cobs_with_scratch(slice(data), &scratch), Vec::new()).unwrap();
+        let buffer = scratch.take();
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(buffer.as_ptr(), pointer);
+
+        assert_eq!(first, second);
+        assert_eq!(first, gen_simple(cobs(slice(data)), Vec::new()).unwrap());
+        assert_eq!(first, vec![0x03, 0x11, 0x22, 0x02, 0x33, 0x00]);
+
+        scratch.set(buffer);
+        let failing = pair(slice(data), |_| Err(GenError::NotYetImplemented));
+        let res = gen_simple(cobs_with_scratch(failing, &scratch), Vec::new());
+        assert!(matches!(res, Err(GenError::NotYetImplemented)));
+        let buffer = scratch.take();
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(buffer.as_ptr(), pointer);
+    }
+
+    #[test]
+    fn test_rle_repetitive() {
+        let data = [0xAAu8; 600];
+        let mut buf = [0u8; 6];
+        let (out, pos) = gen(rle(&data[..], 255), &mut buf[..]).unwrap();
+        assert_eq!(pos, 6);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[255, 0xAA, 255, 0xAA, 90, 0xAA]);
+    }
+
+    #[test]
+    fn test_rle_max_run() {
+        let mut buf = [0u8; 8];
+        let (_, pos) = gen(rle(&[7u8; 10][..], 4), &mut buf[..]).unwrap();
+        assert_eq!(pos, 6);
+        assert_eq!(&buf[..6], &[4, 7, 4, 7, 2, 7]);
+    }
+
+    #[test]
+    fn test_rle_no_runs() {
+        let mut buf = [0u8; 8];
+        let (out, pos) = gen(rle(&b"abcd"[..], 255), &mut buf[..]).unwrap();
+        assert_eq!(pos, 8);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[1, b'a', 1, b'b', 1, b'c', 1, b'd']);
+
+        let mut buf = [0u8; 7];
+        let res = gen(rle(&b"abcd"[..], 255), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_with_checksum_placeholder() {
+        fn additive<W: Write>(data: &[u8]) -> impl SerializeFn<W> {
+            be_u32(data.iter().map(|b| *b as u32).sum())
+        }
+
+        let mut buf = [0u8; 9];
+        let (out, pos) = gen(
+            with_checksum_placeholder(4, tuple((be_u8(1), be_u32(0x0203_0405))), additive),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 9);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[0, 0, 0, 15, 1, 2, 3, 4, 5]);
+
+        // the field is reserved in place, before the body is written
+        let mut buf = [0u8; 3];
+        let res = gen(
+            with_checksum_placeholder(4, be_u8(1), additive),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+
+        let mut buf = [0u8; 9];
+        let res = gen(
+            with_checksum_placeholder(2, be_u8(1), additive),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[test]
+    fn test_split_at() {
+        let mut buf = [0u8; 10];
+        let (out, pos) = gen(
+            tuple((
+                be_u8(0xFF),
+                split_at(tuple((be_u8(1), string("abc"))), |written: &[u8]| {
+                    assert_eq!(written, &[1, b'a', b'b', b'c']);
+                    be_u32(written.len() as u32)
+                }),
+            )),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 9);
+        assert_eq!(out.len(), 1);
+        assert_eq!(&buf[..9], &[0xFF, 1, b'a', b'b', b'c', 0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_zlib_framed() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[0xff; 100_000][..]), 0x149a_302c);
+
+        let mut buf = [0u8; 9];
+        let (out, pos) = gen(
+            zlib_framed(&b"Wikipedia"[..], slice(&[0xaa, 0xbb, 0xcc][..])),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 9);
+        assert!(out.is_empty());
+        assert_eq!(
+            &buf,
+            &[0x78, 0x9c, 0xaa, 0xbb, 0xcc, 0x11, 0xe6, 0x03, 0x98]
+        );
+        assert_eq!(((buf[0] as u16) << 8 | buf[1] as u16) % 31, 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_vectored() {
+        let header = b"head";
+        let body = b"body";
+
+        let v = gen_vectored(tuple((
+            slice_ref(&header[..]),
+            be_u32(0x0102_0304),
+            be_u8(5),
+            string_ref("body"),
+            slice_ref(&body[..]),
+        )))
+        .unwrap();
+        let slices = v.io_slices();
+
+        assert_eq!(slices.len(), 4);
+        assert_eq!(slices[0].as_ptr(), header.as_ptr());
+        assert_eq!(&slices[0][..], b"head");
+        assert_eq!(&slices[1][..], &[1, 2, 3, 4, 5]);
+        assert_eq!(&slices[2][..], b"body");
+        assert_eq!(slices[3].as_ptr(), body.as_ptr());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vectored_copy_threshold() {
+        let first = [1u8; 100];
+        let second = [2u8; 64];
+        let short = [3u8; 63];
+
+        let v = gen_simple(
+            tuple((
+                be_u16(100),
+                slice_ref(&first[..]),
+                be_u16(64),
+                slice_ref(&b"\x00"[..]),
+                slice_ref(&second[..]),
+                slice_ref(&short[..]),
+                be_u8(0),
+            )),
+            Vectored::with_copy_threshold(64),
+        )
+        .unwrap();
+        let slices = v.io_slices();
+
+        assert_eq!(slices.len(), 5);
+        assert_eq!(&slices[0][..], &[0, 100][..]);
+        assert_eq!(slices[1].as_ptr(), first.as_ptr());
+        assert_eq!(slices[1].len(), 100);
+        assert_eq!(&slices[2][..], &[0, 64, 0][..]);
+        assert_eq!(slices[3].as_ptr(), second.as_ptr());
+        assert_eq!(slices[3].len(), 64);
+        assert_eq!(slices[4].len(), 64);
+        assert_ne!(slices[4].as_ptr(), short.as_ptr());
+        assert_eq!(&slices[4][..63], &short[..]);
+        assert_eq!(slices[4][63], 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_fill_remaining() {
+        let mut buf = [0xffu8; 10];
+        let (out, pos) = gen(
+            fill_remaining(tuple((be_u8(1), be_u8(2), be_u8(3)))),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 10);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[1, 2, 3, 1, 2, 3, 1, 2, 3, 0]);
+
+        let mut buf = [0xffu8; 4];
+        let (out, pos) = gen(fill_remaining(string("")), &mut buf[..]).unwrap();
+        assert_eq!(pos, 4);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sized_group() {
+        let mut buf = [0u8; 11];
+        let (out, pos) = gen(
+            sized_group!(
+                |len| be_u32(len as u32),
+                be_u8(1),
+                be_u32(0x0203_0405),
+                be_u16(0x0607)
+            ),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 11);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[0, 0, 0, 7, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_escape() {
+        let escaper = |b: u8| if b == b'<' { Some(&b"&lt;"[..]) } else { None };
+
+        let mut buf = [0u8; 19];
+        let (out, pos) = gen(escape(&b"<a<<bc<"[..], escaper), &mut buf[..]).unwrap();
+        assert_eq!(pos, 19);
+        assert!(out.is_empty());
+        assert_eq!(&buf, b"&lt;a&lt;&lt;bc&lt;");
+
+        let mut buf = [0u8; 4];
+        let (_, pos) = gen(escape(&b"abcd"[..], escaper), &mut buf[..]).unwrap();
+        assert_eq!(pos, 4);
+        assert_eq!(&buf, b"abcd");
+
+        let mut buf = [0u8; 3];
+        let res = gen(escape(&b"a<"[..], escaper), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_named() {
+        let mut buf = [0u8; 10];
+        let (out, pos, trace) = gen_debug_trace(
+            tuple((
+                be_u8(0),
+                named("first", be_u32(1)),
+                be_u8(0),
+                named("second", string("abc")),
+            )),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 9);
+        assert_eq!(out.len(), 1);
+        assert_eq!(trace, vec![("first", 1..5), ("second", 6..9)]);
+        assert_eq!(&buf[trace[1].1.clone()], b"abc");
+    }
+
+    #[test]
+    fn test_template() {
+        let base = [0xaau8; 16];
+        let patches: &[(usize, &[u8])] = &[(2, &[1, 2, 3]), (12, &[4, 5, 6, 7])];
+
+        let mut buf = [0u8; 16];
+        let (out, pos) = gen(template(&base[..], patches), &mut buf[..]).unwrap();
+        assert_eq!(pos, 16);
+        assert!(out.is_empty());
+        assert_eq!(
+            &buf,
+            &[0xaa, 0xaa, 1, 2, 3, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 4, 5, 6, 7]
+        );
+
+        let invalid: &[&[(usize, &[u8])]] = &[
+            &[(14, &[1, 2, 3])],
+            &[(16, &[1])],
+            &[(4, &[1, 2]), (5, &[3])],
+            &[(8, &[1]), (2, &[2])],
+            &[(usize::MAX, &[1])],
+        ];
+        for patches in invalid {
+            let mut buf = [0u8; 16];
+            let res = gen(template(&base[..], patches), &mut buf[..]);
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+            assert_eq!(buf, [0u8; 16]);
+        }
+    }
+
+    #[test]
+    fn test_text_helpers() {
+        let mut buf = [0u8; 3];
+        gen(bom(), &mut buf[..]).unwrap();
+        assert_eq!(&buf, "\u{feff}".as_bytes());
+
+        let mut buf = [0u8; 6];
+        let (_, pos) = gen(line(string("abcd"), LineEnding::Lf), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..5], b"abcd\n");
+
+        let (out, pos) = gen(line(string("abcd"), LineEnding::CrLf), &mut buf[..]).unwrap();
+        assert_eq!(pos, 6);
+        assert!(out.is_empty());
+        assert_eq!(&buf, b"abcd\r\n");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_counted() {
+        use crate::multi::separated_list;
+
+        let values: Vec<u32> = (0..100).collect();
+        let (buf, pos, profile) = gen_count_writes(
+            counted(
+                "list",
+                separated_list(
+                    counted("separator", string(",")),
+                    values.iter().map(|v| counted("element", be_u32(*v))),
+                ),
+            ),
+            Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(pos, 499);
+        assert_eq!(buf.len(), 499);
+        assert_eq!(profile.len(), 3);
+        assert_eq!(
+            profile["element"],
+            CallStats {
+                calls: 100,
+                bytes: 400
+            }
+        );
+        assert_eq!(
+            profile["separator"],
+            CallStats {
+                calls: 99,
+                bytes: 99
+            }
+        );
+        assert_eq!(
+            profile["list"],
+            CallStats {
+                calls: 1,
+                bytes: 499
+            }
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cow() {
+        use std::borrow::Cow;
+
+        fn serializer<W: Write>(owned: bool) -> impl SerializeFn<W> {
+            let data: Cow<'static, str> = if owned {
+                Cow::Owned(format!("{}{}", "ab", "cd"))
+            } else {
+                Cow::Borrowed("abcd")
+            };
+            string(data)
+        }
+
+        for &owned in &[false, true] {
+            let mut buf = [0u8; 4];
+            let (_, pos) = gen(serializer(owned), &mut buf[..]).unwrap();
+            assert_eq!(pos, 4);
+            assert_eq!(&buf, b"abcd");
+        }
+
+        let data: Cow<[u8]> = Cow::Owned(vec![1, 2, 3]);
+        let mut buf = [0u8; 3];
+        gen(slice(data), &mut buf[..]).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_chunked() {
+        let data: [u8; 1000] = {
+            let mut data = [0u8; 1000];
+            for (i, b) in data.iter_mut().enumerate() {
+                *b = i as u8;
+            }
+            data
+        };
+
+        let mut buf = [0u8; 1010];
+        let (out, pos) = gen(chunked(&data[..], 256), &mut buf[..]).unwrap();
+        assert_eq!(pos, 1000 + 4 * 2 + 2);
+        assert!(out.is_empty());
+
+        let mut frames = &buf[..];
+        for (i, &len) in [256usize, 256, 256, 232].iter().enumerate() {
+            assert_eq!(&frames[..2], &(len as u16).to_be_bytes());
+            assert_eq!(&frames[2..2 + len], &data[i * 256..i * 256 + len]);
+            frames = &frames[2 + len..];
+        }
+        assert_eq!(frames, &[0, 0]);
+
+        let mut buf = [0xffu8; 2];
+        let (_, pos) = gen(chunked(&b""[..], 256), &mut buf[..]).unwrap();
+        assert_eq!(pos, 2);
+        assert_eq!(buf, [0, 0]);
+    }
+
+    #[test]
+    fn test_length_suffixed() {
+        let mut buf = [0u8; 14];
+        let (out, pos) = gen(
+            tuple((
+                be_u8(0xff),
+                length_suffixed(tuple((be_u32(1), string("ab"), be_u8(2)))),
+                be_u8(0xee),
+            )),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 13);
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            &buf[..13],
+            &[0xff, 0, 0, 0, 1, b'a', b'b', 2, 0, 0, 0, 7, 0xee]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_text_float() {
+        let cases: &[(f64, usize, &[u8])] = &[
+            (1.23456, 2, b"1.23"),
+            (2.5, 0, b"2"),
+            (-0.125, 2, b"-0.12"),
+            (-12.3456, 3, b"-12.346"),
+            (0.0, 3, b"0.000"),
+            (1e3, 1, b"1000.0"),
+        ];
+
+        for (value, precision, expected) in cases {
+            let mut buf = [0u8; 16];
+            let (_, pos) = gen(text_float(*value, *precision), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], *expected);
+        }
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut buf = [0u8; 8];
+        let (out, pos) = gen(
+            tuple((bounded(be_u32(0x0102_0304), 4), be_u8(5))),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(out.len(), 3);
+        assert_eq!(&buf[..5], &[1, 2, 3, 4, 5]);
+
+        let mut buf = [0u8; 8];
+        let res = gen(bounded(tuple((be_u32(1), be_u8(2))), 4), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::LimitExceeded(1))));
+        assert_eq!(&buf[4..], &[0; 4]);
+
+        let mut buf = [0u8; 2];
+        let res = gen(bounded(be_u32(1), 4), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dns_name() {
+        let mut buf = [0u8; 17];
+        let (out, pos) = gen(dns_name("www.example.com."), &mut buf[..]).unwrap();
+        assert_eq!(pos, 17);
+        assert!(out.is_empty());
+        assert_eq!(&buf, b"\x03www\x07example\x03com\x00");
+
+        for root in &["", "."] {
+            let mut buf = [0xffu8; 1];
+            let (_, pos) = gen(dns_name(root), &mut buf[..]).unwrap();
+            assert_eq!(pos, 1);
+            assert_eq!(buf, [0]);
+        }
+
+        let label_63 = "a".repeat(63);
+        let mut buf = [0u8; 128];
+        let (_, pos) = gen(dns_name(&label_63), &mut buf[..]).unwrap();
+        assert_eq!(pos, 65);
+
+        // 4 labels of 63 bytes take 256 bytes with their lengths and the final zero
+        let too_long = [&label_63[..]; 4].join(".");
+        let invalid = [
+            format!("{}a.com", label_63),
+            "www..com".to_string(),
+            ".com".to_string(),
+            too_long,
+        ];
+        for name in &invalid {
+            let mut buf = [0u8; 300];
+            assert!(matches!(
+                gen(dns_name(name), &mut buf[..]),
+                Err(GenError::InvalidValue)
+            ));
+            assert_eq!(buf[0], 0);
+        }
+    }
+
+    #[test]
+    fn test_xml_escaping() {
+        let data = "<a href=\"x\">Tom & 'Jerry'</a>";
+
+        let mut buf = [0u8; 64];
+        let (_, pos) = gen(xml_text(data), &mut buf[..]).unwrap();
+        assert_eq!(
+            &buf[..pos as usize],
+            &b"&lt;a href=\"x\"&gt;Tom &amp; 'Jerry'&lt;/a&gt;"[..]
+        );
+
+        let mut buf = [0u8; 80];
+        let (_, pos) = gen(xml_attr(data), &mut buf[..]).unwrap();
+        assert_eq!(
+            &buf[..pos as usize],
+            &b"&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"[..]
+        );
+    }
+
+    #[test]
+    fn test_length_value() {
+        fn body<W: Write>() -> impl SerializeFn<W> {
+            tuple((be_u8(1), string("abc"), be_u32(0x0203_0405)))
+        }
+
+        let mut patched = [0u8; 12];
+        let (_, pos) = gen(length_value(body()), &mut patched[..]).unwrap();
+        assert_eq!(pos, 12);
+
+        let mut measured = [0u8; 12];
+        let (_, pos) = gen(
+            sized_group!(|len| be_u32(len as u32), body()),
+            &mut measured[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 12);
+
+        assert_eq!(patched, measured);
+        assert_eq!(&patched, &[0, 0, 0, 8, 1, b'a', b'b', b'c', 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_recursive() {
+        enum Tree {
+            Leaf(u8),
+            Node(Box<Tree>, u8, Box<Tree>),
+        }
+
+        let tree = Tree::Node(
+            Box::new(Tree::Node(
+                Box::new(Tree::Leaf(1)),
+                2,
+                Box::new(Tree::Leaf(3)),
+            )),
+            4,
+            Box::new(Tree::Leaf(5)),
+        );
+
+        // pre-order: tag, value, then the left and right subtrees for nodes
+        fn tree_serializer<'a, W: Write + 'a>() -> impl Fn(&'a Tree) -> BoxedSerializeFn<'a, W> {
+            recursive(|tree, t: &'a Tree| match t {
+                Tree::Leaf(v) => Box::new(tuple((be_u8(0), be_u8(*v)))),
+                Tree::Node(left, v, right) => {
+                    Box::new(tuple((be_u8(1), be_u8(*v), tree(left), tree(right))))
+                }
+            })
+        }
+
+        let mut buf = [0u8; 10];
+        let (out, pos) = gen(tree_serializer()(&tree), &mut buf[..]).unwrap();
+        assert_eq!(pos, 10);
+        assert!(out.is_empty());
+        assert_eq!(&buf, &[1, 4, 1, 2, 0, 1, 0, 3, 0, 5][..]);
+    }
+
+    #[test]
+    fn test_xor_mask() {
+        use crate::bytes::be_u8;
+        use crate::sequence::pair;
+
+        let key = [0x01, 0x02, 0x04, 0x08];
+        let mut buf = [0u8; 8];
+        let (out, pos) = gen(
+            pair(be_u8(0xff), xor_mask(slice(&[0x10; 6][..]), &key[..])),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 7);
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            &buf[..],
+            &[0xff, 0x11, 0x12, 0x14, 0x18, 0x11, 0x12, 0x00][..]
+        );
+
+        let mut reverted = [0u8; 6];
+        gen(xor_mask(slice(&buf[1..7]), &key[..]), &mut reverted[..]).unwrap();
+        assert_eq!(reverted, [0x10; 6]);
+
+        let mut small = [0u8; 2];
+        let res = gen(xor_mask(slice(&[0x10; 6][..]), &key[..]), &mut small[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+    }
+
+    #[test]
+    fn test_len_prefixed_string_be_u32() {
+        let data = "h\u{e9}\u{2603}!";
+        assert_eq!(data.chars().count(), 4);
+
+        let mut buf = [0u8; 11];
+        let (out, pos) = gen(len_prefixed_string_be_u32(data), &mut buf[..]).unwrap();
+        assert_eq!(pos, 11);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..], &b"\x00\x00\x00\x07h\xc3\xa9\xe2\x98\x83!"[..]);
+
+        let mut buf = [0u8; 4];
+        let (_, pos) = gen(len_prefixed_string_be_u32(""), &mut buf[..]).unwrap();
+        assert_eq!(pos, 4);
+        assert_eq!(buf, [0; 4]);
+    }
+
+    #[test]
+    fn test_offset() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 13];
+        let out = string("head")(WriteContext::from(&mut buf[..])).unwrap();
+        let (out, first) = offset(pair(be_u8(1), be_u8(2)))(out).unwrap();
+        let (out, second) = offset(string("xy"))(out).unwrap();
+        assert_eq!((first, second), (4, 6));
+
+        // write the offset table after the fields
+        let out = pair(be_u32(first as u32), be_u8(second as u8))(out).unwrap();
+        assert_eq!(out.position, 13);
+        assert!(out.write.is_empty());
+        assert_eq!(&buf[..], &b"head\x01\x02xy\x00\x00\x00\x04\x06"[..]);
+
+        let mut buf = [0u8; 3];
+        let serializer = pair(
+            be_u8(0xff),
+            and_then(offset(be_u8(0)), |start| be_u8(start as u8)),
+        );
+        gen(serializer, &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff, 0, 1]);
+    }
+
+    #[test]
+    fn test_alt() {
+        use crate::bytes::be_u8;
+        use crate::sequence::pair;
+
+        // writes as many bytes of `data` as possible among 8, 4 and 2, after their count
+        fn compact(data: &[u8]) -> impl SerializeFn<&mut [u8]> {
+            alt([8, 4, 2]
+                .iter()
+                .map(move |&n| pair(be_u8(n as u8), slice(&data[..n]))))
+        }
+
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+
+        let mut buf = [0xaau8; 4];
+        let (out, pos) = gen(compact(&data), &mut buf[..]).unwrap();
+        assert_eq!(pos, 3);
+        assert_eq!(out.len(), 1);
+        assert_eq!(&buf[..], &[2, 0x11, 0x22, 0][..]);
+
+        // the first candidate fits, so nothing past its output is touched
+        let mut buf = [0xaau8; 12];
+        let (out, pos) = gen(compact(&data), &mut buf[..]).unwrap();
+        assert_eq!(pos, 9);
+        assert_eq!(out.len(), 3);
+        assert_eq!(
+            &buf[..],
+            &[8, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0xaa, 0xaa, 0xaa][..]
+        );
+
+        let mut buf = [0u8; 2];
+        assert!(matches!(
+            gen(compact(&data), &mut buf[..]),
+            Err(GenError::BufferTooSmall(1))
+        ));
+
+        let fail = |_: WriteContext<&mut [u8]>| Err(GenError::CustomError(1));
+        assert!(matches!(
+            gen(alt([fail].iter()), &mut buf[..]),
+            Err(GenError::CustomError(1))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_atomic() {
+        use crate::bytes::be_u32;
+        use crate::sequence::pair;
+
+        let mut buf = [0xaau8; 6];
+        let res = gen(
+            pair(string("ab"), atomic(pair(be_u32(1), be_u32(2)))),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+        assert_eq!(&buf[..], &[b'a', b'b', 0xaa, 0xaa, 0xaa, 0xaa][..]);
+
+        let fail = |_: WriteContext<Vec<u8>>| Err(GenError::CustomError(1));
+        let res = gen(atomic(pair(be_u32(1), fail)), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::CustomError(1))));
+        assert_eq!(&buf[..], &[b'a', b'b', 0xaa, 0xaa, 0xaa, 0xaa][..]);
+
+        let (out, pos) = gen(pair(string("ab"), atomic(be_u32(1))), &mut buf[..]).unwrap();
+        assert_eq!(pos, 6);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..], &[b'a', b'b', 0, 0, 0, 1][..]);
+    }
+
+    #[test]
+    fn test_null_padded_string() {
+        let mut buf = [0xffu8; 100];
+
+        let (_, pos) = gen(null_padded_string("ab", 5), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..6], &b"ab\x00\x00\x00\xff"[..]);
+
+        let (_, pos) = gen(null_padded_string("abcde", 5), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..6], &b"abcde\xff"[..]);
+
+        let (_, pos) = gen(null_padded_string("a", 100), &mut buf[..]).unwrap();
+        assert_eq!(pos, 100);
+        assert_eq!(buf[0], b'a');
+        assert!(buf[1..].iter().all(|&b| b == 0));
+
+        let mut buf = [0xffu8; 8];
+        let res = gen(null_padded_string("abcdef", 5), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+        assert_eq!(buf, [0xff; 8]);
+
+        let res = gen(null_padded_string("ab", 5), &mut buf[..4]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_null_terminated_padded_string() {
+        let mut buf = [0xffu8; 8];
+
+        let (_, pos) = gen(null_terminated_padded_string("ab", 5), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..6], &b"ab\x00\x00\x00\xff"[..]);
+
+        let (_, pos) = gen(null_terminated_padded_string("abcd", 5), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..6], &b"abcd\x00\xff"[..]);
+
+        let res = gen(null_terminated_padded_string("abcde", 5), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+    }
+
+    #[test]
+    fn test_envelope() {
+        use crate::bytes::{be_u16, be_u32, be_u8};
+
+        fn record<'a, F: SerializeFn<&'a mut [u8]>>(body: F) -> impl SerializeFn<&'a mut [u8]> {
+            envelope(be_u16(0xabcd), 4, |len| be_u32(len as u32), body)
+        }
+
+        let mut buf = [0u8; 18];
+        let (out, pos) = gen(record(record(be_u8(0x42))), &mut buf[..]).unwrap();
+        assert_eq!(pos, 17);
+        assert_eq!(out.len(), 1);
+
+        // the outer length covers the inner record, markers included
+        assert_eq!(&buf[..6], &[0xab, 0xcd, 0, 0, 0, 9][..]);
+        assert_eq!(&buf[6..15], &[0xab, 0xcd, 0, 0, 0, 1, 0x42, 0xab, 0xcd][..]);
+        assert_eq!(&buf[15..17], &[0xab, 0xcd][..]);
+
+        let mut buf = [0u8; 20];
+        let res = gen(
+            envelope(be_u8(0), 2, |len| be_u8(len as u8), be_u8(1)),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooBig(1))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_copy_reader() {
+        use std::io::{Cursor, ErrorKind};
+
+        let data: Vec<u8> = (0..2000u32).map(|i| i as u8).collect();
+
+        let mut buf = vec![0u8; 1500];
+        let (out, pos) = gen(copy_reader(Cursor::new(&data), 1500), &mut buf[..]).unwrap();
+        assert_eq!(pos, 1500);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..], &data[..1500]);
+
+        let res = gen(copy_reader(Cursor::new(&data[..10]), 11), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof));
+
+        let res = gen(copy_reader(Cursor::new(&data), 1000), &mut buf[..600]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(400))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_copy_reader_checked() {
+        use std::io::{Cursor, ErrorKind};
+
+        let data: Vec<u8> = (0..2000u32).map(|i| i as u8).collect();
+        let mut reader = Cursor::new(&data);
+
+        let mut small = vec![0u8; 600];
+        let res = gen(copy_reader_checked(&mut reader, 1000), &mut small[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(400))));
+
+        // nothing was read by the failed attempt
+        let mut buf = vec![0u8; 1500];
+        let (out, pos) = gen(copy_reader_checked(&mut reader, 1000), &mut buf[..]).unwrap();
+        assert_eq!(pos, 1000);
+        assert_eq!(out.len(), 500);
+        assert_eq!(&buf[..1000], &data[..1000]);
+
+        let res = gen(
+            copy_reader_checked(Cursor::new(&data[..10]), 11),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_ascii_case() {
+        let data = "Mixed CASE \u{c9}t\u{e9} 123 -_";
+
+        let mut buf = [0u8; 100];
+        let (_, pos) = gen(ascii_lowercase(data), &mut buf[..]).unwrap();
+        assert_eq!(pos, data.len() as u64);
+        assert_eq!(
+            &buf[..data.len()],
+            "mixed case \u{c9}t\u{e9} 123 -_".as_bytes()
+        );
+
+        let (_, pos) = gen(ascii_uppercase(data), &mut buf[..]).unwrap();
+        assert_eq!(pos, data.len() as u64);
+        assert_eq!(
+            &buf[..data.len()],
+            "MIXED CASE \u{c9}T\u{e9} 123 -_".as_bytes()
+        );
+
+        let long = "aB".repeat(50);
+        let (_, pos) = gen(ascii_uppercase(&long), &mut buf[..]).unwrap();
+        assert_eq!(pos, 100);
+        assert!(buf.iter().all(|&b| b == b'A' || b == b'B'));
+
+        let res = gen(ascii_lowercase(&long), &mut buf[..70]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(30))));
+    }
+
+    #[test]
+    fn test_base64() {
+        let mut buf = [0u8; 100];
+
+        let cases: &[(&[u8], &[u8])] = &[
+            (b"", b""),
+            (b"f", b"Zg=="),
+            (b"fo", b"Zm8="),
+            (b"foo", b"Zm9v"),
+            (b"foob", b"Zm9vYg=="),
+            (b"\xff\xfe\x00\x01\x7f", b"//4AAX8="),
+        ];
+        for (data, encoded) in cases {
+            let (_, pos) = gen(base64(data), &mut buf[..]).unwrap();
+            assert_eq!(pos, encoded.len() as u64);
+            assert_eq!(base64_max_size(data.len()), encoded.len());
+            assert_eq!(&buf[..encoded.len()], *encoded);
+        }
+
+        // several chunks of 48 input bytes
+        let data = [0u8; 70];
+        let (_, pos) = gen(base64(&data[..]), &mut buf[..]).unwrap();
+        assert_eq!(pos, 96);
+        assert!(buf[..94].iter().all(|&c| c == b'A'));
+        assert_eq!(&buf[94..96], b"==");
+
+        let res = gen(base64(&data[..]), &mut buf[..80]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(16))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_with_offset_comments() {
+        let data: Vec<u8> = (0..40).collect();
+        let dump = gen_simple(with_offset_comments(slice(&data)), Vec::new()).unwrap();
+        let dump = String::from_utf8(dump).unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "00000000: 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010: 10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f"
+        );
+        assert_eq!(lines[2], "00000020: 20 21 22 23 24 25 26 27");
+
+        let empty = gen_simple(with_offset_comments(slice(&[][..])), Vec::new()).unwrap();
+        assert!(empty.is_empty());
+
+        // the first line takes 58 bytes, the second one does not fit in the 12 left
+        let mut buf = [0u8; 70];
+        let res = gen(with_offset_comments(slice(&data)), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(46))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_unix_timestamp_be_u64() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let fixed = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let serializer = unix_timestamp_be_u64(move || fixed);
+
+        let first = gen_simple(&serializer, Vec::new()).unwrap();
+        let second = gen_simple(&serializer, Vec::new()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, 1_700_000_000u64.to_be_bytes());
+
+        let before_epoch = || UNIX_EPOCH - Duration::from_secs(1);
+        let res = gen_simple(unix_timestamp_be_u64(before_epoch), Vec::new());
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+    }
+
+    #[test]
+    fn test_concat_framed() {
+        let parts = [&b"a"[..], &b""[..], &b"bcd"[..]];
+        let mut buf = [0u8; 16];
+        let (_, pos) = gen(concat_framed(&parts, 3), &mut buf[..]).unwrap();
+        assert_eq!(pos, 13);
+        assert_eq!(&buf[..13], &b"\x00\x00\x01a\x00\x00\x00\x00\x00\x03bcd"[..]);
+
+        let (_, pos) = gen(concat_framed(&parts, 1), &mut buf[..]).unwrap();
+        assert_eq!(pos, 7);
+        assert_eq!(&buf[..7], &b"\x01a\x00\x03bcd"[..]);
+
+        // a 256 bytes part does not fit a one byte prefix, and nothing is written
+        let mut buf = [0xffu8; 300];
+        let parts = [&b"a"[..], &[0u8; 256][..]];
+        assert!(matches!(
+            gen(concat_framed(&parts, 1), &mut buf[..]),
+            Err(GenError::InvalidValue)
+        ));
+        assert_eq!(buf[0], 0xff);
+
+        let (_, pos) = gen(concat_framed(&parts, 2), &mut buf[..]).unwrap();
+        assert_eq!(pos, 261);
+        assert_eq!(&buf[3..5], &[0x01, 0x00][..]);
+    }
+
+    #[test]
+    fn test_map_input() {
+        struct Record {
+            id: u32,
+            name: &'static str,
+        }
+
+        let mut buf = [0u8; 6];
+        let record = Record {
+            id: 0x0102_0304,
+            name: "ab",
+        };
+
+        {
+            let id = map_input(|r: &Record| r.id, be_u32);
+            let name = map_input(|r: &Record| r.name, string);
+
+            let (out, pos) = gen(tuple((id(&record), name(&record))), &mut buf[..]).unwrap();
+            assert_eq!(pos, 6);
+            assert!(out.is_empty());
+        }
+        assert_eq!(buf, [1, 2, 3, 4, b'a', b'b']);
+    }
+
+    #[test]
+    fn test_trailing_crc_and_length() {
+        let mut buf = [0u8; 51];
+        let data = b"The quick brown fox jumps over the lazy dog";
+        let (out, pos) = gen(
+            trailing_crc_and_length(slice(&data[..]), FooterOrder::CrcFirst),
             &mut buf[..],
         )
         .unwrap();
+        assert_eq!(pos, 51);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..43], &data[..]);
+        assert_eq!(&buf[43..], &[0x41, 0x4f, 0xa3, 0x39, 0, 0, 0, 43][..]);
 
-        assert!(new_buf.is_empty());
+        let res = gen(
+            trailing_crc_and_length(slice(&data[..]), FooterOrder::CrcFirst),
+            &mut buf[..50],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+
+        let mut buf = [0xffu8; 8];
+        let (_, pos) = gen(
+            trailing_crc_and_length(slice(&b""[..]), FooterOrder::LengthFirst),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 8);
+        assert_eq!(buf, [0; 8]);
+    }
+
+    #[test]
+    fn test_right_aligned() {
+        let mut buf = [0u8; 40];
+        let (_, pos) = gen(number_right_aligned(42, 5, b' '), &mut buf[..]).unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..5], &b"   42"[..]);
+
+        let (_, pos) = gen(number_right_aligned(0, 3, b'0'), &mut buf[..]).unwrap();
+        assert_eq!(pos, 3);
+        assert_eq!(&buf[..3], &b"000"[..]);
+
+        let (_, pos) = gen(number_right_aligned(u64::MAX, 20, b' '), &mut buf[..]).unwrap();
+        assert_eq!(pos, 20);
+        assert_eq!(&buf[..20], &b"18446744073709551615"[..]);
+
+        let (_, pos) = gen(octal_right_aligned(u64::MAX, 22, b'0'), &mut buf[..]).unwrap();
+        assert_eq!(pos, 22);
+        assert_eq!(&buf[..22], &b"1777777777777777777777"[..]);
+
+        // padding longer than the internal chunk
+        let (_, pos) = gen(octal_right_aligned(8, 40, b'0'), &mut buf[..]).unwrap();
+        assert_eq!(pos, 40);
+        assert_eq!(&buf[36..], &b"0010"[..]);
+        assert!(buf.iter().all(|&b| b == b'0' || b == b'1'));
+
+        for &(value, width) in &[(100_000, 5), (7, 0)] {
+            let res = gen(number_right_aligned(value, width, b' '), &mut buf[..]);
+            assert!(matches!(res, Err(GenError::InvalidValue)));
+        }
+
+        let res = gen(octal_right_aligned(0o777, 4, b'0'), &mut buf[..3]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dictionary_entry() {
+        use crate::sequence::pair;
+
+        fn entry<'d>(dictionary: &'d Dictionary, data: &'d str) -> impl SerializeFn<Vec<u8>> + 'd {
+            dictionary_entry(
+                dictionary,
+                data,
+                |data: &[u8]| tuple((be_u8(0), be_u8(data.len() as u8), slice(data.to_vec()))),
+                |position| pair(be_u8(1), be_u16(position as u16)),
+            )
+        }
+
+        let dictionary = Dictionary::new();
+        let out = crate::internal::gen_simple(
+            tuple((
+                entry(&dictionary, "abc"),
+                entry(&dictionary, "de"),
+                entry(&dictionary, "abc"),
+                entry(&dictionary, "de"),
+            )),
+            Vec::new(),
+        )
+        .unwrap();
         assert_eq!(
-            &buf,
-            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+            &out[..],
+            &b"\x00\x03abc\x00\x02de\x01\x00\x00\x01\x00\x05"[..]
         );
+        assert_eq!(dictionary.len(), 2);
+
+        // a failed entry is not added, so it is written again next time
+        let dictionary = Dictionary::new();
+        let mut short = [0u8; 4];
+        let mut buf = [0u8; 4];
+        let new = |data: &[u8]| slice(data.to_vec());
+        let reference = |position| be_u8(position as u8);
+        assert!(gen(
+            dictionary_entry(&dictionary, &b"abcde"[..], new, reference),
+            &mut short[..]
+        )
+        .is_err());
+        assert!(dictionary.is_empty());
+        let (_, pos) = gen(
+            pair(
+                dictionary_entry(&dictionary, &b"ab"[..], new, reference),
+                dictionary_entry(&dictionary, &b"ab"[..], new, reference),
+            ),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 3);
+        assert_eq!(&buf[..3], &b"ab\x00"[..]);
+    }
+
+    #[test]
+    fn test_zero_remaining() {
+        let mut buf = [0xaau8; 16];
+        let (out, pos) = gen(zero_remaining(be_u32(0x0102_0304)), &mut buf[..]).unwrap();
+        assert_eq!(pos, 16);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..4], &[1, 2, 3, 4][..]);
+        assert_eq!(&buf[4..], &[0u8; 12][..]);
+
+        // the position keeps counting from where the output started
+        let mut buf = [0xaau8; 16];
+        let (out, pos) = gen(
+            tuple((be_u16(0x0506), zero_remaining(be_u8(7)))),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 16);
+        assert!(out.is_empty());
+        assert_eq!(&buf[..4], &[5, 6, 7, 0][..]);
     }
 
     #[cfg(feature = "std")]
     #[test]
-    fn test_back_to_the_buffer_vec() {
-        let buf = Vec::new();
+    fn test_checksum_regions() {
+        use crate::sequence::pair;
 
-        let buf = gen_simple(
+        let regions = Regions::new();
+        let mut buf = [0u8; 64];
+        let (_, pos) = gen(
             tuple((
-                back_to_the_buffer(
-                    4,
-                    move |buf| gen(string("test"), buf),
-                    move |buf, len| gen_simple(be_u32(len as u32), buf),
+                checksum_region(&regions, "header", be_u32(0x0102_0304)),
+                region_crc32(&regions, "header"),
+                checksum_region(
+                    &regions,
+                    "body",
+                    string("The quick brown fox jumps over the lazy dog"),
                 ),
-                be_u8(42),
+                region_crc32(&regions, "body"),
+                region_crc32(&regions, "header"),
             )),
-            buf,
+            &mut buf[..],
         )
         .unwrap();
+        assert_eq!(pos, 4 + 4 + 43 + 4 + 4);
+        assert_eq!(&buf[..4], &[1, 2, 3, 4][..]);
+        assert_eq!(&buf[4..8], &[0xb6, 0x3c, 0xfb, 0xcd][..]);
+        assert_eq!(&buf[51..55], &[0x41, 0x4f, 0xa3, 0x39][..]);
+        assert_eq!(&buf[55..59], &buf[4..8]);
 
-        assert_eq!(
-            &buf[..],
-            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+        // checksums can only be written after their region
+        let regions = Regions::new();
+        let res = gen(
+            pair(
+                region_crc32(&regions, "a"),
+                checksum_region(&regions, "a", be_u8(0)),
+            ),
+            &mut buf[..],
         );
+        assert!(matches!(res, Err(GenError::InvalidValue)));
     }
 
     #[test]
-    fn test_back_to_the_buffer_cursor() {
-        let mut buf = [0; 9];
-        {
-            let cursor = crate::lib::std::io::Cursor::new(&mut buf[..]);
-            let cursor = gen_simple(
-                tuple((
-                    back_to_the_buffer(
-                        4,
-                        move |buf| gen(string("test"), buf),
-                        move |buf, len| gen_simple(be_u32(len as u32), buf),
-                    ),
-                    be_u8(42),
-                )),
-                cursor,
-            )
-            .unwrap();
-            assert_eq!(cursor.position(), 9);
-        }
+    fn test_pad_to_multiple() {
+        use crate::sequence::pair;
+
+        let mut buf = [0xaau8; 40];
+        let (_, pos) = gen(pad_to_multiple(slice(&[1u8; 10][..]), 16, 0), &mut buf[..]).unwrap();
+        assert_eq!(pos, 16);
+        assert_eq!(&buf[..10], &[1u8; 10][..]);
+        assert_eq!(&buf[10..16], &[0u8; 6][..]);
+        assert_eq!(buf[16], 0xaa);
+
+        let (_, pos) = gen(pad_to_multiple(slice(&[2u8; 16][..]), 16, 0), &mut buf[..]).unwrap();
+        assert_eq!(pos, 16);
+        assert_eq!(&buf[..16], &[2u8; 16][..]);
+
+        // the padding only depends on the body, not on what was written before
+        let (_, pos) = gen(
+            pair(be_u8(3), pad_to_multiple(slice(&[4u8; 3][..]), 4, 5)),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 5);
+        assert_eq!(&buf[..5], &[3, 4, 4, 4, 5][..]);
+
+        // padding longer than the internal chunk
+        let (_, pos) = gen(pad_to_multiple(be_u8(6), 40, 7), &mut buf[..]).unwrap();
+        assert_eq!(pos, 40);
+        assert_eq!(buf[0], 6);
+        assert_eq!(&buf[1..], &[7u8; 39][..]);
+
+        let res = gen(pad_to_multiple(be_u8(6), 16, 0), &mut buf[..8]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(8))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_pointers() {
+        let pointers = Pointers::new();
+        let mut buf = [0u8; 32];
+        let (_, pos) = gen(
+            tuple((
+                pointer_be_u32(&pointers, "data"),
+                pointer_target(&pointers, "header"),
+                string("head"),
+                pointer_be_u32(&pointers, "header"),
+                pointer_target(&pointers, "data"),
+                be_u16(0xabcd),
+            )),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(pos, 14);
+        assert_eq!(&buf[..4], &[0, 0, 0, 0][..]);
+
+        pointers.resolve(&mut buf[..]).unwrap();
         assert_eq!(
-            &buf,
-            &[0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+            &buf[..14],
+            &b"\x00\x00\x00\x0chead\x00\x00\x00\x04\xab\xcd"[..]
         );
+
+        // a pointer to a missing target cannot be resolved
+        let pointers = Pointers::new();
+        gen(pointer_be_u32(&pointers, "missing"), &mut buf[..]).unwrap();
+        assert!(matches!(
+            pointers.resolve(&mut buf[..]),
+            Err(GenError::InvalidValue)
+        ));
     }
 
     #[test]
-    fn test_back_to_the_buffer_cursor_counter() {
-        let mut buf = [0; 10];
+    fn test_text_bool() {
+        let mut buf = [0u8; 8];
+        for &(value, true_word, false_word, expected) in &[
+            (true, "true", "false", &b"true"[..]),
+            (false, "true", "false", &b"false"[..]),
+            (true, "yes", "no", &b"yes"[..]),
+            (false, "yes", "no", &b"no"[..]),
+        ] {
+            let (_, pos) = gen(text_bool(value, true_word, false_word), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], expected);
+        }
+
+        let res = gen(text_bool(false, "true", "false"), &mut buf[..3]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[test]
+    fn test_tagged_union() {
+        use crate::bytes::be_i16;
+        use crate::sequence::pair;
+
+        enum Message<'a> {
+            Ping,
+            Data(&'a str),
+            Move { x: i16, y: i16 },
+        }
+
+        fn message<'a, 'm>(m: &'m Message<'m>) -> impl SerializeFn<&'a mut [u8]> + 'm
+        where
+            'a: 'm,
         {
-            let cursor = crate::lib::std::io::Cursor::new(&mut buf[..]);
-            let (cursor, pos) = gen(
-                tuple((
-                    be_u8(64),
-                    back_to_the_buffer(
-                        4,
-                        &move |buf| gen(string("test"), buf),
-                        &move |buf, len| gen_simple(be_u32(len as u32), buf),
-                    ),
-                    be_u8(42),
-                )),
-                cursor,
+            tagged_union!(m, be_u16;
+                Message::Ping => (0x10, skip(0)),
+                Message::Data(data) => (0x20, pair(be_u8(data.len() as u8), string(data))),
+                Message::Move { x, y } => (0x30, pair(be_i16(*x), be_i16(*y))),
             )
-            .unwrap();
-            assert_eq!(pos, 10);
-            assert_eq!(cursor.position(), 10);
         }
+
+        let mut buf = [0u8; 8];
+        let (_, pos) = gen(message(&Message::Ping), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &[0x00, 0x10][..]);
+
+        let (_, pos) = gen(message(&Message::Data("abc")), &mut buf[..]).unwrap();
+        assert_eq!(&buf[..pos as usize], &b"\x00\x20\x03abc"[..]);
+
+        let (_, pos) = gen(message(&Message::Move { x: -1, y: 2 }), &mut buf[..]).unwrap();
         assert_eq!(
-            &buf,
-            &[64, 0, 0, 0, 4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8, 42]
+            &buf[..pos as usize],
+            &[0x00, 0x30, 0xff, 0xff, 0x00, 0x02][..]
         );
     }
+
+    #[test]
+    fn test_csv_field() {
+        let mut buf = [0u8; 16];
+        for &(field, expected) in &[
+            ("plain", &b"plain"[..]),
+            ("", &b""[..]),
+            ("a,b", &b"\"a,b\""[..]),
+            ("say \"hi\"", &b"\"say \"\"hi\"\"\""[..]),
+            ("\"", &b"\"\"\"\""[..]),
+            ("l1\r\nl2", &b"\"l1\r\nl2\""[..]),
+        ] {
+            let (_, pos) = gen(csv_field(field), &mut buf[..]).unwrap();
+            assert_eq!(&buf[..pos as usize], expected);
+        }
+
+        assert!(matches!(
+            gen(csv_field("a,b"), &mut buf[..4]),
+            Err(GenError::BufferTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn test_duration_be_u64() {
+        use crate::lib::std::time::Duration;
+
+        let duration = Duration::new(3, 456_789_012);
+        let mut buf = [0u8; 8];
+        gen(duration_millis_be_u64(duration), &mut buf[..]).unwrap();
+        assert_eq!(u64::from_be_bytes(buf), 3_456);
+        gen(duration_micros_be_u64(duration), &mut buf[..]).unwrap();
+        assert_eq!(u64::from_be_bytes(buf), 3_456_789);
+        gen(duration_nanos_be_u64(duration), &mut buf[..]).unwrap();
+        assert_eq!(u64::from_be_bytes(buf), 3_456_789_012);
+
+        // about 584 years is the largest number of nanoseconds an u64 can hold
+        let limit = Duration::from_nanos(u64::MAX);
+        gen(duration_nanos_be_u64(limit), &mut buf[..]).unwrap();
+        assert_eq!(buf, [0xff; 8]);
+
+        let large = limit + Duration::from_nanos(1);
+        let res = gen(duration_nanos_be_u64(large), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+        gen(duration_micros_be_u64(large), &mut buf[..]).unwrap();
+        assert_eq!(u64::from_be_bytes(buf), u64::MAX / 1_000);
+
+        let res = gen(duration_millis_be_u64(Duration::MAX), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_json_number() {
+        let mut buf = [0u8; 32];
+
+        for &(value, expected) in &[
+            (1.0, "1"),
+            (-3.0, "-3"),
+            (0.1, "0.1"),
+            (-2.5, "-2.5"),
+            (1e300, "1e300"),
+            (1.5e-7, "1.5e-7"),
+        ] {
+            let (_, pos) = gen(json_number(value), &mut buf[..]).unwrap();
+            let text = std::str::from_utf8(&buf[..pos as usize]).unwrap();
+            assert_eq!(text, expected);
+            assert_eq!(text.parse::<f64>().unwrap(), value);
+        }
+
+        for &value in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                gen(json_number(value), &mut buf[..]),
+                Err(GenError::InvalidValue)
+            ));
+        }
+    }
 }
@@ -17,6 +17,16 @@ pub enum GenError {
     BufferTooBig(usize),
     /// Operation asked for accessing an invalid index
     InvalidOffset,
+    /// The value cannot be represented in the requested format
+    InvalidValue,
+    /// A `bounded` serializer tried to write past its limit. Argument is the number of
+    /// bytes over the limit that were required
+    LimitExceeded(usize),
+    /// A serializer run with `gen_expect` did not write the expected number of bytes
+    LengthMismatch { expected: usize, actual: usize },
+    /// A serializer run with `gen_checked_capacity!` did not fit in the buffer. `required`
+    /// is the total length of its output, `available` the length of the buffer
+    CapacityRequired { required: usize, available: usize },
     /// IoError returned by Write
     IoError(io::Error),
 
@@ -51,6 +61,65 @@ pub trait SerializeFn<W>: Fn(WriteContext<W>) -> GenResult<W> {}
 
 impl<W, F: Fn(WriteContext<W>) -> GenResult<W>> SerializeFn<W> for F {}
 
+/// Boxed serializing function
+///
+/// Boxing erases the type of a serializer, which is needed for recursive serializers: see
+/// `combinator::recursive`.
+#[cfg(feature = "std")]
+pub type BoxedSerializeFn<'a, W> = Box<dyn Fn(WriteContext<W>) -> GenResult<W> + 'a>;
+
+/// Types which are returned from value returning serializing functions
+pub type GenResultV<W, T> = Result<(WriteContext<W>, T), GenError>;
+
+/// Trait for serializing functions that also return a value
+///
+/// Like `SerializeFn`, but besides the updated `WriteContext`, these functions return a
+/// value computed while serializing, like the offset where something was written. See
+/// `combinator::with_value`, `combinator::without_value` and `combinator::and_then` to
+/// convert between both kinds of serializers.
+///
+/// This trait is implemented for all `Fn(WriteContext<W>) -> GenResultV<W, T>`.
+pub trait SerializeFnV<W, T>: Fn(WriteContext<W>) -> GenResultV<W, T> {}
+
+impl<W, T, F: Fn(WriteContext<W>) -> GenResultV<W, T>> SerializeFnV<W, T> for F {}
+
+/// Trait for serializers that know an upper bound of the length of their output
+///
+/// This is implemented by the `bytes::Be` and `bytes::Le` number wrappers, by
+/// `combinator::Base64`, and by tuples of `MaxSize` serializers, which are written one after
+/// the other. See `gen_with_max_size`.
+pub trait MaxSize<W> {
+    /// Upper bound of the number of bytes written by `serialize`
+    fn max_size(&self) -> usize;
+
+    /// Writes the value to the output
+    fn serialize(&self, w: WriteContext<W>) -> GenResult<W>;
+}
+
+// Implements MaxSize for a tuple, from its type parameters and their indices,
+// e.g. A 0, B 1.
+macro_rules! max_size_tuple_impl(
+  ($($name:ident $idx:tt),+) => (
+    impl<W, $($name: MaxSize<W>),+> MaxSize<W> for ( $($name,)+ ) {
+      fn max_size(&self) -> usize {
+        0 $(+ self.$idx.max_size())+
+      }
+
+      fn serialize(&self, w: WriteContext<W>) -> GenResult<W> {
+        $(let w = self.$idx.serialize(w)?;)+
+        Ok(w)
+      }
+    }
+  );
+);
+
+max_size_tuple_impl!(A 0);
+max_size_tuple_impl!(A 0, B 1);
+max_size_tuple_impl!(A 0, B 1, C 2);
+max_size_tuple_impl!(A 0, B 1, C 2, D 3);
+max_size_tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+max_size_tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+
 /// Context around a `Write` impl that is passed through serializing functions
 ///
 /// Currently this only keeps track of the current write position since the start of serialization.
@@ -79,64 +148,1683 @@ impl<W: Write> Write for WriteContext<W> {
         Ok(amt)
     }
 
-    #[cfg(feature = "std")]
-    fn flush(&mut self) -> io::Result<()> {
-        self.write.flush()
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+impl<W: Seek> io::Seek for WriteContext<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let old_pos = self.write.seek(SeekFrom::Current(0))?;
+        let new_pos = self.write.seek(pos)?;
+        if new_pos >= old_pos {
+            self.position += new_pos - old_pos;
+        } else {
+            self.position -= old_pos - new_pos;
+        }
+        Ok(new_pos)
+    }
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w` and returns the result
+///
+/// This internally wraps `w` in a `WriteContext`, starting at position 0.
+///
+/// ```rust
+/// use cookie_factory::{gen, combinator::slice};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(slice(&b"abcd"[..]), &mut buf[..]).unwrap();
+///   assert_eq!(buf.len(), 100 - 4);
+///   assert_eq!(pos, 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// ```
+pub fn gen<W: Write, F: SerializeFn<W>>(f: F, w: W) -> Result<(W, u64), GenError> {
+    f(WriteContext::from(w)).map(|ctx| ctx.into_inner())
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w` and returns the updated `w`
+///
+/// This internally wraps `w` in a `WriteContext`, starting at position 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_simple, combinator::slice};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let buf = gen_simple(slice(&b"abcd"[..]), &mut buf[..]).unwrap();
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// ```
+pub fn gen_simple<W: Write, F: SerializeFn<W>>(f: F, w: W) -> Result<W, GenError> {
+    f(WriteContext::from(w)).map(|ctx| ctx.into_inner().0)
+}
+
+/// Runs the given serializer `f` on a `[u8; N]` array and returns the array
+///
+/// This is meant for small records of a known size, and does not allocate. The serializer
+/// must write exactly `N` bytes: if it writes less, `GenError::BufferTooBig` is returned
+/// with the number of bytes left, and if it needs more, it fails with
+/// `GenError::BufferTooSmall`.
+///
+/// ```rust
+/// use cookie_factory::{gen_array, bytes::be_u32, sequence::pair};
+///
+/// let buf: [u8; 8] = gen_array(pair(be_u32(1), be_u32(2))).unwrap();
+///
+/// assert_eq!(buf, [0, 0, 0, 1, 0, 0, 0, 2]);
+/// ```
+pub fn gen_array<const N: usize, F>(f: F) -> Result<[u8; N], GenError>
+where
+    F: SerializeFn<io::Cursor<[u8; N]>>,
+{
+    let cursor = gen_simple(f, io::Cursor::new([0u8; N]))?;
+    let written = cursor.position() as usize;
+    if written < N {
+        return Err(GenError::BufferTooBig(N - written));
+    }
+
+    Ok(cursor.into_inner())
+}
+
+/// Splits `buf` into a reserved region of `reserved` bytes and the rest of the buffer
+///
+/// This is the manual form of `back_to_the_buffer`: the rest can be written first, then
+/// the reserved region can be filled with values depending on it, like a length or a
+/// checksum. Both returned slices mutably borrow `buf`, so `buf` itself cannot be used
+/// until they are dropped. Since `gen` returns the unwritten part of the buffer, the
+/// written part of `rest` has to be tracked through the returned position.
+///
+/// ```rust
+/// use cookie_factory::{gen, gen_reserve_then_fill, combinator::string, bytes::be_u16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (reserved, rest) = gen_reserve_then_fill(&mut buf[..], 2).unwrap();
+///   let (_, len) = gen(string("abcd"), rest).unwrap();
+///   gen(be_u16(len as u16), reserved).unwrap();
+/// }
+///
+/// assert_eq!(&buf[..6], &b"\x00\x04abcd"[..]);
+/// ```
+pub fn gen_reserve_then_fill(
+    buf: &mut [u8],
+    reserved: usize,
+) -> Result<(&mut [u8], &mut [u8]), GenError> {
+    if buf.len() < reserved {
+        return Err(GenError::BufferTooSmall(reserved - buf.len()));
+    }
+
+    Ok(buf.split_at_mut(reserved))
+}
+
+/// Runs the given serializer `f` on `buf`, starting at `offset`, and returns the offset
+/// right after the last byte written
+///
+/// If `offset` is past the end of `buf`, this fails with `GenError::InvalidOffset`, and
+/// with `GenError::BufferTooSmall` if `f` does not fit after it.
+///
+/// ```rust
+/// use cookie_factory::{gen_at, combinator::string};
+///
+/// let mut buf = [0u8; 8];
+///
+/// let end = gen_at(string("ab"), &mut buf[..], 2).unwrap();
+/// let end = gen_at(string("cd"), &mut buf[..], end + 1).unwrap();
+///
+/// assert_eq!(end, 7);
+/// assert_eq!(&buf[..], &b"\x00\x00ab\x00cd\x00"[..]);
+/// ```
+pub fn gen_at<'a, F: SerializeFn<&'a mut [u8]>>(
+    f: F,
+    buf: &'a mut [u8],
+    offset: usize,
+) -> Result<usize, GenError> {
+    if offset > buf.len() {
+        return Err(GenError::InvalidOffset);
+    }
+
+    let (_, written) = gen(f, &mut buf[offset..])?;
+    Ok(offset + written as usize)
+}
+
+/// Runs the given serializer `f` on `buf` and returns the part of `buf` that was written
+///
+/// This is the prefix of `buf` of the length `gen` returns as position.
+///
+/// Panics if `f` does not return the part of the slice following what it wrote.
+///
+/// ```rust
+/// use cookie_factory::{gen_ref, combinator::string};
+///
+/// let mut buf = [0u8; 8];
+///
+/// let written = gen_ref(string("abc"), &mut buf[..]).unwrap();
+///
+/// assert_eq!(written, &b"abc"[..]);
+/// ```
+pub fn gen_ref<'a, F: SerializeFn<&'a mut [u8]>>(
+    f: F,
+    buf: &'a mut [u8],
+) -> Result<&'a [u8], GenError> {
+    let (written, _) = split_written(f, WriteContext::from(buf), "gen_ref")?;
+    Ok(written)
+}
+
+// Applies `f` to the slice of `out`, and returns the bytes it wrote along with the context
+// following them
+//
+// Panics, with a message starting with `caller`, if `f` does not return the part of the
+// slice following what it wrote.
+pub(crate) fn split_written<'a, F: SerializeFn<&'a mut [u8]>>(
+    f: F,
+    out: WriteContext<&'a mut [u8]>,
+    caller: &str,
+) -> Result<(&'a mut [u8], WriteContext<&'a mut [u8]>), GenError> {
+    let ptr = out.write.as_mut_ptr();
+    let available = out.write.len();
+    let position = out.position;
+
+    // SAFETY: this is the slice `out` wrapped, which is not used after this point, so `f`
+    // gets the only reference to it.
+    let write = unsafe { crate::lib::std::slice::from_raw_parts_mut(ptr, available) };
+    let out = f(WriteContext { write, position })?;
+    let len = out.position.wrapping_sub(position) as usize;
+    assert!(
+        len <= available && crate::lib::std::ptr::eq(out.write.as_ptr(), ptr.wrapping_add(len)),
+        "{}: the serializer did not advance the output slice",
+        caller
+    );
+
+    // SAFETY: the assert above checks that `f` consumed exactly the first `len` bytes of
+    // the slice and returned the ones following them, which are dropped here. Both parts
+    // are derived from `ptr` and do not overlap.
+    let (written, write) = unsafe {
+        (
+            crate::lib::std::slice::from_raw_parts_mut(ptr, len),
+            crate::lib::std::slice::from_raw_parts_mut(ptr.add(len), available - len),
+        )
+    };
+    Ok((
+        written,
+        WriteContext {
+            write,
+            position: out.position,
+        },
+    ))
+}
+
+/// Runs the given serializer `f` on `buf` and returns the rest of `buf` with the number of
+/// bytes written
+///
+/// The count is how far the returned slice starts after the start of `buf`, so it covers
+/// every byte `f` went over, without another pass on the output.
+///
+/// Panics if `f` does not return a part of `buf`.
+///
+/// ```rust
+/// use cookie_factory::{gen_measured, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let mut buf = [0u8; 8];
+///
+/// let (rest, written) = gen_measured(pair(be_u16(3), string("abc")), &mut buf[..]).unwrap();
+///
+/// assert_eq!(written, 5);
+/// assert_eq!(rest.len(), 3);
+/// ```
+pub fn gen_measured<'a, F: SerializeFn<&'a mut [u8]>>(
+    f: F,
+    buf: &'a mut [u8],
+) -> Result<(&'a mut [u8], usize), GenError> {
+    let start = buf.as_ptr() as usize;
+    let available = buf.len();
+
+    let rest = gen_simple(f, buf)?;
+    let written = (rest.as_ptr() as usize).checked_sub(start);
+    let written = match written {
+        Some(written) if written <= available => written,
+        _ => panic!("gen_measured: the serializer did not return the rest of the output slice"),
+    };
+    Ok((rest, written))
+}
+
+/// Runs the given serializer `f` on `buf` after setting all its bytes to zero
+///
+/// Serializers like `skip` or `back_to_the_buffer` can leave bytes of the output untouched,
+/// which then keep what the buffer held before. Clearing the buffer first makes the output
+/// only depend on the serializer, and prevents leaking the previous contents.
+///
+/// ```rust
+/// use cookie_factory::{gen_deterministic, combinator::{skip, string}, sequence::pair};
+///
+/// let mut buf = [0xffu8; 8];
+///
+/// {
+///   let (buf, pos) = gen_deterministic(pair(skip(2), string("ab")), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 8 - 4);
+/// }
+///
+/// assert_eq!(&buf[..], &b"\x00\x00ab\x00\x00\x00\x00"[..]);
+/// ```
+pub fn gen_deterministic<'a, F: SerializeFn<&'a mut [u8]>>(
+    f: F,
+    buf: &'a mut [u8],
+) -> Result<(&'a mut [u8], u64), GenError> {
+    buf.fill(0);
+    gen(f, buf)
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w` and checks that it wrote exactly
+/// `expected_len` bytes
+///
+/// This is meant to catch serializers drifting from the length given by a format
+/// specification. If another number of bytes was written, this fails with
+/// `GenError::LengthMismatch`.
+///
+/// ```rust
+/// use cookie_factory::{gen_expect, GenError, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen_expect(pair(be_u16(4), string("abcd")), &mut buf[..], 6).unwrap();
+///   assert_eq!(pos, 6);
+///   assert_eq!(buf.len(), 100 - 6);
+/// }
+///
+/// assert_eq!(&buf[..6], &b"\x00\x04abcd"[..]);
+///
+/// match gen_expect(string("abc"), &mut buf[..], 4) {
+///   Err(GenError::LengthMismatch { expected: 4, actual: 3 }) => {},
+///   _ => panic!("expected a length mismatch"),
+/// }
+/// ```
+pub fn gen_expect<W: Write, F: SerializeFn<W>>(
+    f: F,
+    w: W,
+    expected_len: usize,
+) -> Result<(W, u64), GenError> {
+    let (w, written) = gen(f, w)?;
+    if written != expected_len as u64 {
+        return Err(GenError::LengthMismatch {
+            expected: expected_len,
+            actual: written as usize,
+        });
+    }
+
+    Ok((w, written))
+}
+
+/// Checks that a serializer writes `expected`, and reports running out of space
+/// correctly, for property tests and fuzz targets
+///
+/// `serialize` runs the serializer on the given slice and returns the number of bytes
+/// written, usually with `|out| gen(f, out).map(|(_, pos)| pos)`. It is run on slices of
+/// every length shorter than `expected`, which must fail with `GenError::BufferTooSmall`
+/// holding at least 1 and at most the number of missing bytes, then on a slice of the
+/// exact length and on a larger one, which must both write `expected`.
+///
+/// Panics if any of those checks fails.
+///
+/// ```rust
+/// use cookie_factory::{gen, gen_fuzz_target, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// gen_fuzz_target(
+///   |out| gen(pair(be_u16(3), string("abc")), out).map(|(_, pos)| pos),
+///   b"\0\x03abc",
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_fuzz_target<S>(serialize: S, expected: &[u8])
+where
+    S: Fn(&mut [u8]) -> Result<u64, GenError>,
+{
+    let len = expected.len();
+    let mut buf = vec![0u8; len + 1];
+
+    for available in 0..len {
+        match serialize(&mut buf[..available]) {
+            Err(GenError::BufferTooSmall(n)) => assert!(
+                n > 0 && n <= len - available,
+                "gen_fuzz_target: {} bytes reported missing with {} of {} bytes available",
+                n,
+                available,
+                len
+            ),
+            Err(e) => panic!("gen_fuzz_target: unexpected error: {:?}", e),
+            Ok(_) => panic!(
+                "gen_fuzz_target: {} bytes should be too short for {} bytes",
+                available, len
+            ),
+        }
+    }
+
+    for available in [len, len + 1] {
+        let buf = &mut buf[..available];
+        let written = serialize(buf).expect("gen_fuzz_target: serialization failed");
+        assert_eq!(written, len as u64, "gen_fuzz_target: wrong length written");
+        assert_eq!(
+            &buf[..len],
+            expected,
+            "gen_fuzz_target: wrong bytes written"
+        );
+    }
+}
+
+/// Runs the given serializer `f` and returns an iterator over its output, in chunks of
+/// `chunk_size` bytes
+///
+/// The chunks are copied out of a `gen_partial_resume` state: `f` runs once, on the first
+/// call to `next`, and its output is buffered until all the chunks are taken, so
+/// serializers keeping state between runs are applied exactly once. Every chunk is
+/// `chunk_size` bytes long, except for the last one which can be shorter. If the
+/// serializer fails, its error is the only item.
+///
+/// Panics if `chunk_size` is 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_iter, combinator::string};
+///
+/// let chunks: Vec<Vec<u8>> = gen_iter(string("abcdefgh"), 3).collect::<Result<_, _>>().unwrap();
+///
+/// assert_eq!(chunks, vec![b"abc".to_vec(), b"def".to_vec(), b"gh".to_vec()]);
+/// ```
+///
+/// With the `async` feature, `gen_streaming_async` writes the chunks to a
+/// `futures_io::AsyncWrite` instead.
+#[cfg(feature = "std")]
+pub fn gen_iter<F: SerializeFn<Vec<u8>>>(
+    f: F,
+    chunk_size: usize,
+) -> impl Iterator<Item = Result<Vec<u8>, GenError>> {
+    assert!(chunk_size > 0, "gen_iter: chunk_size must be at least 1");
+
+    let mut state = gen_partial_resume(f);
+
+    crate::lib::std::iter::from_fn(move || {
+        if state.is_done() {
+            return None;
+        }
+
+        let mut chunk = vec![0u8; chunk_size];
+        match state.fill(&mut chunk[..]) {
+            Ok(0) => None,
+            Ok(written) => {
+                chunk.truncate(written);
+                Some(Ok(chunk))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// Runs the given serializer `f` and writes its output to the async `writer`, in chunks of
+/// `chunk_size` bytes
+///
+/// This is only available with the `async` feature. Like `gen_iter`, the chunks are
+/// taken from a `gen_partial_resume` state, which runs `f` once and buffers its output,
+/// and they go through a single buffer of `chunk_size` bytes. The future yields whenever
+/// `writer` is not ready. `writer` is flushed at the end, and the number of bytes written
+/// is returned.
+///
+/// Panics if `chunk_size` is 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_streaming_async, GenError, combinator::string};
+/// use futures_io::AsyncWrite;
+///
+/// async fn send<W: AsyncWrite + Unpin>(socket: &mut W) -> Result<u64, GenError> {
+///   gen_streaming_async(string("hello"), 4096, socket).await
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub async fn gen_streaming_async<F, A>(
+    f: F,
+    chunk_size: usize,
+    writer: &mut A,
+) -> Result<u64, GenError>
+where
+    F: SerializeFn<Vec<u8>>,
+    A: futures_io::AsyncWrite + Unpin,
+{
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    assert!(
+        chunk_size > 0,
+        "gen_streaming_async: chunk_size must be at least 1"
+    );
+
+    let mut state = gen_partial_resume(f);
+    let mut buf = vec![0u8; chunk_size];
+
+    while !state.is_done() {
+        let len = state.fill(&mut buf[..])?;
+        let mut chunk = &buf[..len];
+
+        while !chunk.is_empty() {
+            let n = poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, chunk)).await?;
+            if n == 0 {
+                return Err(GenError::IoError(std::io::ErrorKind::WriteZero.into()));
+            }
+            chunk = &chunk[n..];
+        }
+    }
+
+    poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)).await?;
+    Ok(state.position())
+}
+
+/// Runs the given serializer `f` into a temporary `Vec<u8>`, then writes its whole output
+/// to `writer`
+///
+/// Returns the number of bytes written. This is the simplest way to serialize to a file
+/// or a socket: the output goes through a single `write_all` call, and the serializer
+/// does not depend on `writer`'s type.
+///
+/// ```rust
+/// use cookie_factory::{gen_to_writer, combinator::string};
+///
+/// let mut file: Vec<u8> = Vec::new();
+///
+/// let len = gen_to_writer(string("abcd"), &mut file).unwrap();
+///
+/// assert_eq!(len, 4);
+/// assert_eq!(&file[..], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_to_writer<F: SerializeFn<Vec<u8>>, W: Write + ?Sized>(
+    f: F,
+    writer: &mut W,
+) -> Result<usize, GenError> {
+    let buf = gen_simple(f, Vec::new())?;
+    writer.write_all(&buf)?;
+    Ok(buf.len())
+}
+
+/// Runs the given serializer `f` at the current position of `cursor`, then returns the
+/// new position of the cursor
+///
+/// Like any other write to the cursor, this overwrites the bytes following its position,
+/// and grows the underlying `Vec<u8>` as needed. This is useful to interleave serializers
+/// with other code writing to the same cursor. The position of the `WriteContext` passed
+/// to `f` still starts at 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_into_cursor, combinator::string};
+/// use std::io::Cursor;
+///
+/// let mut cursor = Cursor::new(b"abcd".to_vec());
+/// cursor.set_position(2);
+///
+/// let position = gen_into_cursor(string("xyz"), &mut cursor).unwrap();
+///
+/// assert_eq!(position, 5);
+/// assert_eq!(&cursor.get_ref()[..], &b"abxyz"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_into_cursor<'a, F: SerializeFn<&'a mut io::Cursor<Vec<u8>>>>(
+    f: F,
+    cursor: &'a mut io::Cursor<Vec<u8>>,
+) -> Result<u64, GenError> {
+    let (cursor, _) = gen(f, cursor)?;
+    Ok(cursor.position())
+}
+
+/// Runs the given serializer `f` into a new `Vec<u8>`, then patches the pointer fields it
+/// recorded in `pointers` with the positions of their targets
+///
+/// See `combinator::pointer_be_u32` and `combinator::Pointers::resolve`. The pointers can
+/// refer to targets written before or after them, since they are only resolved once the
+/// whole output is laid out.
+///
+/// ```rust
+/// use cookie_factory::{gen_with_backrefs, combinator::{pointer_be_u32, pointer_target, string, Pointers}, sequence::tuple};
+///
+/// let pointers = Pointers::new();
+/// let buf = gen_with_backrefs(
+///   tuple((pointer_be_u32(&pointers, "body"), string("xy"), pointer_target(&pointers, "body"), string("z"))),
+///   &pointers,
+/// ).unwrap();
+///
+/// assert_eq!(&buf[..], &b"\x00\x00\x00\x06xyz"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_with_backrefs<F: SerializeFn<Vec<u8>>>(
+    f: F,
+    pointers: &crate::combinator::Pointers,
+) -> Result<Vec<u8>, GenError> {
+    let mut buf = gen_simple(f, Vec::new())?;
+    pointers.resolve(&mut buf)?;
+    Ok(buf)
+}
+
+/// Reusable output buffer, for serializing many messages without allocating for each one
+///
+/// `serialize` clears the buffer before each message, which keeps its capacity, so once
+/// it has grown to the size of the largest message no more allocation is needed.
+///
+/// ```rust
+/// use cookie_factory::{Serializer, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let mut serializer = Serializer::with_capacity(64);
+///
+/// assert_eq!(serializer.serialize(pair(be_u16(2), string("ab"))).unwrap(), &b"\x00\x02ab"[..]);
+/// assert_eq!(serializer.serialize(string("c")).unwrap(), &b"c"[..]);
+/// assert_eq!(serializer.capacity(), 64);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Serializer {
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl Serializer {
+    /// Creates a `Serializer` with an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `Serializer` with a buffer that can hold `capacity` bytes without
+    /// reallocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Serializer {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of bytes the buffer can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Clears the buffer, runs the given serializer `f` on it and returns the bytes it
+    /// wrote
+    ///
+    /// The returned slice borrows the buffer, so it must be dropped before serializing
+    /// the next message.
+    pub fn serialize<'a, F: SerializeFn<&'a mut Vec<u8>>>(
+        &'a mut self,
+        f: F,
+    ) -> Result<&'a [u8], GenError> {
+        self.buf.clear();
+        let (buf, _) = gen(f, &mut self.buf)?;
+        let buf: &'a Vec<u8> = buf;
+        Ok(&buf[..])
+    }
+}
+
+/// Runs the given serializer `f` into a new `Vec<u8>`, then pads it with zeros until its
+/// length is a multiple of `align`
+///
+/// Panics if `align` is 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_aligned, combinator::string};
+///
+/// let buf = gen_aligned(string("abcde"), 4).unwrap();
+///
+/// assert_eq!(&buf[..], &b"abcde\x00\x00\x00"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_aligned<F: SerializeFn<Vec<u8>>>(f: F, align: usize) -> Result<Vec<u8>, GenError> {
+    assert!(align > 0, "gen_aligned: align must be at least 1");
+
+    let mut buf = gen_simple(f, Vec::new())?;
+    let padding = (align - buf.len() % align) % align;
+    buf.resize(buf.len() + padding, 0);
+    Ok(buf)
+}
+
+/// Runs the given serializer `s` into a new `Vec<u8>` allocated once for its `max_size`
+///
+/// If `s` writes more than `max_size` bytes, this fails with `GenError::LimitExceeded`.
+///
+/// ```rust
+/// use cookie_factory::{gen_with_max_size, bytes::Be, combinator::Base64};
+///
+/// let buf = gen_with_max_size((Be(5u16), Base64(b"hello"))).unwrap();
+///
+/// assert_eq!(&buf[..], &b"\x00\x05aGVsbG8="[..]);
+/// assert_eq!(buf.capacity(), 10);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_with_max_size<S: MaxSize<Vec<u8>>>(s: S) -> Result<Vec<u8>, GenError> {
+    let max_size = s.max_size();
+    let buf = gen_simple(|w| s.serialize(w), Vec::with_capacity(max_size))?;
+    if buf.len() > max_size {
+        return Err(GenError::LimitExceeded(buf.len() - max_size));
+    }
+
+    Ok(buf)
+}
+
+/// Runs the given serializer `f` into a new `Vec<u8>` and returns its bytes as a lowercase
+/// hexadecimal string
+///
+/// This is mostly useful to write assertions in tests.
+///
+/// ```rust
+/// use cookie_factory::{gen_to_hex, bytes::{be_u16, be_u8}, sequence::pair};
+///
+/// let hex = gen_to_hex(pair(be_u16(1), be_u8(0xff))).unwrap();
+///
+/// assert_eq!(hex, "0001ff");
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_to_hex<F: SerializeFn<Vec<u8>>>(f: F) -> Result<String, GenError> {
+    use std::fmt::Write;
+
+    let buf = gen_simple(f, Vec::new())?;
+    let mut hex = String::with_capacity(buf.len() * 2);
+    for byte in buf {
+        write!(hex, "{:02x}", byte).expect("writing to a String cannot fail");
+    }
+    Ok(hex)
+}
+
+/// Runs a header and a body into a new `Vec<u8>`, where the header depends on the total
+/// length
+///
+/// `header` is called with the total length of the output, header included, and returns
+/// the header serializer. It is first applied with a length of 0 to reserve its space,
+/// then `body` is applied after it, and the header is written again over the reserved
+/// space with the final length. The header must write the same number of bytes whatever
+/// the length, otherwise this fails with `GenError::LengthMismatch`.
+///
+/// ```rust
+/// use cookie_factory::{gen_segmented, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let buf = gen_segmented(
+///   |total| pair(string("HD"), be_u16(total as u16)),
+///   string("abcd"),
+/// ).unwrap();
+///
+/// assert_eq!(&buf[..], &b"HD\x00\x08abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_segmented<H, G, B>(header: H, body: B) -> Result<Vec<u8>, GenError>
+where
+    H: Fn(u64) -> G,
+    G: SerializeFn<Vec<u8>>,
+    B: SerializeFn<Vec<u8>>,
+{
+    let (buf, header_len) = gen(header(0), Vec::new())?;
+    let (mut buf, total) = body(WriteContext {
+        write: buf,
+        position: header_len,
+    })?
+    .into_inner();
+
+    let patched = gen_simple(header(total), Vec::new())?;
+    if patched.len() as u64 != header_len {
+        return Err(GenError::LengthMismatch {
+            expected: header_len as usize,
+            actual: patched.len(),
+        });
+    }
+    buf[..patched.len()].copy_from_slice(&patched);
+
+    Ok(buf)
+}
+
+/// Runs a header and a body into two separate `Vec<u8>`, where the header depends on the
+/// length of the body
+///
+/// `body` is applied first, then `header` is called with the length of its output and the
+/// returned serializer is applied to a new `Vec<u8>`. This is meant for protocols sending
+/// the header and the body separately. Both outputs start at position 0.
+///
+/// ```rust
+/// use cookie_factory::{gen_split_header_body, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let (header, body) = gen_split_header_body(
+///   |len| pair(string("HD"), be_u16(len as u16)),
+///   string("abcd"),
+/// ).unwrap();
+///
+/// assert_eq!(&header[..], &b"HD\x00\x04"[..]);
+/// assert_eq!(&body[..], &b"abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_split_header_body<H, G, B>(header: H, body: B) -> Result<(Vec<u8>, Vec<u8>), GenError>
+where
+    H: Fn(u64) -> G,
+    G: SerializeFn<Vec<u8>>,
+    B: SerializeFn<Vec<u8>>,
+{
+    let body = gen_simple(body, Vec::new())?;
+    let header = gen_simple(header(body.len() as u64), Vec::new())?;
+    Ok((header, body))
+}
+
+/// `Write` backend that only counts the bytes written to it
+///
+/// This is used to get the length of a serializer's output without storing it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    len: u64,
+}
+
+impl Counter {
+    /// Creates a `Counter` starting at 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if nothing was written yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Write for Counter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.len += data.len() as u64;
+        Ok(data.len())
+    }
+
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the given serializer `f` on a `Counter` and returns the length of its output
+///
+/// ```rust
+/// use cookie_factory::{gen_len, bytes::be_u32, combinator::string, sequence::pair};
+///
+/// assert_eq!(gen_len(pair(be_u32(1), string("abcd"))).unwrap(), 8);
+/// ```
+pub fn gen_len<F: SerializeFn<Counter>>(f: F) -> Result<u64, GenError> {
+    gen(f, Counter::new()).map(|(_, len)| len)
+}
+
+/// Runs a serializer on a slice like `gen`, reporting the total length it needs if the
+/// slice is too small
+///
+/// `gen_checked_capacity!(f, buf)` runs `f` on the `&mut [u8]` `buf`. If this fails with
+/// `GenError::BufferTooSmall`, which only covers the field that did not fit, it runs `f`
+/// again on a `Counter` and fails with `GenError::CapacityRequired` instead, holding the
+/// length of the whole output. The caller can then allocate a large enough buffer and
+/// retry. Since the serializer expression is evaluated once for each pass, it should not
+/// have side effects.
+///
+/// ```rust
+/// use cookie_factory::{gen_checked_capacity, GenError, combinator::string, sequence::pair};
+///
+/// let mut buf = [0u8; 4];
+///
+/// match gen_checked_capacity!(pair(string("abc"), string("defgh")), &mut buf[..]) {
+///   Err(GenError::CapacityRequired { required: 8, available: 4 }) => {},
+///   _ => panic!("expected the required capacity"),
+/// }
+///
+/// let mut buf = [0u8; 8];
+/// let (_, pos) = gen_checked_capacity!(pair(string("abc"), string("defgh")), &mut buf[..]).unwrap();
+/// assert_eq!(pos, 8);
+/// ```
+#[macro_export]
+macro_rules! gen_checked_capacity(
+  ($f:expr, $buf:expr) => ({
+    let buf: &mut [u8] = $buf;
+    let available = buf.len();
+    match $crate::gen($f, buf) {
+      Err($crate::GenError::BufferTooSmall(_)) => match $crate::gen_len($f) {
+        Ok(required) => Err($crate::GenError::CapacityRequired {
+          required: required as usize,
+          available,
+        }),
+        Err(e) => Err(e),
+      },
+      res => res,
+    }
+  });
+);
+
+/// `Write` backend appending to the `Vec<u8>` held in a `Cell`
+///
+/// The bytes go straight to the buffer in the cell, so it stays with its owner along with
+/// its allocation even if serialization fails. This is used by `cobs_with_scratch`.
+#[cfg(feature = "std")]
+pub struct CellVec<'a> {
+    cell: &'a std::cell::Cell<Vec<u8>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> CellVec<'a> {
+    /// Wraps `cell`, appending to the buffer it holds
+    pub fn new(cell: &'a std::cell::Cell<Vec<u8>>) -> Self {
+        CellVec { cell }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Write for CellVec<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut buf = self.cell.take();
+        buf.extend_from_slice(data);
+        self.cell.set(buf);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `Write` backend used by `gen_partial`, counting the bytes written to a slice in a
+/// counter owned by the caller, so that it is kept when serialization fails
+#[derive(Debug)]
+pub struct Partial<'a> {
+    buf: &'a mut [u8],
+    written: &'a crate::lib::std::cell::Cell<usize>,
+}
+
+impl<'a> Write for Partial<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let amt = self.buf.write(data)?;
+        self.written.set(self.written.get() + amt);
+        Ok(amt)
+    }
+
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the given serializer `f` on `buf`, counting the bytes written in `written` even if
+/// it fails
+///
+/// `written` is reset to 0 first. It is borrowed rather than returned because the serializer
+/// is typed for the `Partial` write it receives, which cannot borrow a counter from this
+/// function. Every byte copied to `buf` is counted, including those of a write that did not
+/// completely fit, so on `GenError::BufferTooSmall` the first `written` bytes of `buf` hold
+/// the start of the output.
+///
+/// ```rust
+/// use cookie_factory::{gen_partial, GenError, combinator::string, sequence::pair};
+/// use std::cell::Cell;
+///
+/// let mut buf = [0u8; 6];
+/// let written = Cell::new(0);
+/// let res = gen_partial(pair(string("abcd"), string("efgh")), &mut buf[..], &written);
+///
+/// assert_eq!(written.get(), 6);
+/// assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+/// assert_eq!(&buf[..written.get()], &b"abcdef"[..]);
+/// ```
+pub fn gen_partial<'a, F: SerializeFn<Partial<'a>>>(
+    f: F,
+    buf: &'a mut [u8],
+    written: &'a crate::lib::std::cell::Cell<usize>,
+) -> Result<(), GenError> {
+    written.set(0);
+    gen_simple(f, Partial { buf, written }).map(|_| ())
+}
+
+/// Serialization state that can be fed successive buffers, created by `gen_partial_resume`
+///
+/// Each call to `fill` writes the next bytes of the output to the given buffer, resuming
+/// exactly where the previous buffer ended, even in the middle of a value.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct PartialResume<F> {
+    f: Option<F>,
+    pending: Vec<u8>,
+    offset: usize,
+    position: u64,
+}
+
+#[cfg(feature = "std")]
+impl<F: SerializeFn<Vec<u8>>> PartialResume<F> {
+    /// Writes the next bytes of the output to `buf`, returning how many were written
+    ///
+    /// The first call runs the serializer, and returns its error if it fails. This returns
+    /// fewer bytes than `buf.len()` only once the end of the output is reached, and 0 after
+    /// that.
+    pub fn fill(&mut self, buf: &mut [u8]) -> Result<usize, GenError> {
+        if let Some(f) = self.f.take() {
+            self.pending = gen_simple(f, Vec::new())?;
+        }
+
+        let available = &self.pending[self.offset..];
+        let written = crate::lib::std::cmp::min(buf.len(), available.len());
+        buf[..written].copy_from_slice(&available[..written]);
+        self.offset += written;
+        self.position += written as u64;
+
+        if self.offset == self.pending.len() {
+            // release the buffer once it is drained
+            self.pending = Vec::new();
+            self.offset = 0;
+        }
+        Ok(written)
+    }
+
+    /// Returns the number of bytes written to the buffers so far
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns true once the whole output was written
+    pub fn is_done(&self) -> bool {
+        self.f.is_none() && self.pending.is_empty()
+    }
+}
+
+/// Returns a `PartialResume` writing the output of `f` to successive buffers
+///
+/// This is meant for pumping data to a fixed size buffer, like the one of a socket,
+/// without stopping on `GenError::BufferTooSmall`. `f` is run once, on the first call to
+/// `fill`, into a `Vec<u8>` holding the bytes that are not written to a buffer yet, which
+/// the following calls drain. Serializers keeping state between runs, like
+/// `dictionary_entry` or `copy_reader`, are then applied exactly once.
+///
+/// ```rust
+/// use cookie_factory::{gen_partial_resume, combinator::string, sequence::pair};
+///
+/// let mut state = gen_partial_resume(pair(string("abcd"), string("efgh")));
+/// let mut buf = [0u8; 3];
+/// let mut out = Vec::new();
+///
+/// while !state.is_done() {
+///   let written = state.fill(&mut buf[..]).unwrap();
+///   out.extend_from_slice(&buf[..written]);
+/// }
+///
+/// assert_eq!(&out[..], &b"abcdefgh"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_partial_resume<F: SerializeFn<Vec<u8>>>(f: F) -> PartialResume<F> {
+    PartialResume {
+        f: Some(f),
+        pending: Vec::new(),
+        offset: 0,
+        position: 0,
+    }
+}
+
+/// Serializes `body` into the caller owned `scratch` buffer, then runs the serializer
+/// `encode` returns for those bytes on `w`
+///
+/// This is the two pass pattern of encodings like COBS or base64, or of checksums computed
+/// over the body, without allocating a temporary buffer on every call: `scratch` is
+/// cleared first and keeps its allocation, so reusing it across calls only allocates while
+/// it grows. It stays with the caller whether serialization succeeds or not.
+///
+/// ```rust
+/// use cookie_factory::{gen_with_scratch, combinator::{base64, string}};
+///
+/// let mut scratch = Vec::new();
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen_with_scratch(string("hello"), &mut scratch, base64, &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"aGVsbG8="[..]);
+/// assert_eq!(&scratch[..], &b"hello"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_with_scratch<'s, W: Write, F, H, G>(
+    body: F,
+    scratch: &'s mut Vec<u8>,
+    encode: H,
+    w: W,
+) -> Result<(W, u64), GenError>
+where
+    F: SerializeFn<&'s mut Vec<u8>>,
+    H: FnOnce(&'s [u8]) -> G,
+    G: SerializeFn<W>,
+{
+    scratch.clear();
+    let data = gen_simple(body, scratch)?;
+    gen(encode(&data[..]), w)
+}
+
+/// `Write` backend over a slice of possibly uninitialized bytes
+///
+/// Bytes are only ever written, never read, and the first ones are initialized in
+/// order, so after writing `n` bytes, the first `n` bytes of the slice are initialized.
+/// Use `gen_into_uninit` to run a serializer on it.
+#[derive(Debug)]
+pub struct UninitSlice<'a> {
+    buf: &'a mut [crate::lib::std::mem::MaybeUninit<u8>],
+}
+
+impl<'a> UninitSlice<'a> {
+    /// Wraps `buf`, writing from its first byte
+    pub fn new(buf: &'a mut [crate::lib::std::mem::MaybeUninit<u8>]) -> Self {
+        Self { buf }
+    }
+
+    /// Returns the number of bytes that can still be written
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if the slice is full
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+impl<'a> Write for UninitSlice<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let amt = crate::lib::std::cmp::min(data.len(), self.buf.len());
+        let (a, b) = crate::lib::std::mem::take(&mut self.buf).split_at_mut(amt);
+        for (dst, &src) in a.iter_mut().zip(data) {
+            *dst = crate::lib::std::mem::MaybeUninit::new(src);
+        }
+        self.buf = b;
+        Ok(amt)
+    }
+
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the given serializer `f` on the possibly uninitialized `buf`, and returns the
+/// number of bytes written
+///
+/// The first bytes of `buf` are initialized, up to the returned length. This avoids
+/// zeroing a buffer that will be overwritten anyway, like the spare capacity of a `Vec`.
+///
+/// ```rust
+/// use cookie_factory::{gen_into_uninit, combinator::string};
+///
+/// let mut v: Vec<u8> = Vec::with_capacity(16);
+///
+/// let len = gen_into_uninit(string("abcd"), v.spare_capacity_mut()).unwrap();
+/// // SAFETY: `gen_into_uninit` initialized the first `len` bytes of the spare capacity
+/// unsafe { v.set_len(len) };
+///
+/// assert_eq!(&v[..], &b"abcd"[..]);
+/// ```
+pub fn gen_into_uninit<'a, F: SerializeFn<UninitSlice<'a>>>(
+    f: F,
+    buf: &'a mut [crate::lib::std::mem::MaybeUninit<u8>],
+) -> Result<usize, GenError> {
+    gen(f, UninitSlice::new(buf)).map(|(_, len)| len as usize)
+}
+
+/// `Write` backend spreading the output over a list of slices, filling them in order
+///
+/// A single write can span several slices, so every serializer writing through
+/// `Write::write`, like the number serializers of the `bytes` module, `slice` or `string`,
+/// handles a value crossing the end of a slice. `Skip` and `BackToTheBuffer` are not
+/// implemented, so `skip` and `back_to_the_buffer` cannot be used with it.
+///
+/// The slices of the list are advanced past the bytes written to them. Use
+/// `gen_multi_buffer` to run a serializer on it.
+#[derive(Debug)]
+pub struct Scatter<'a, 'b> {
+    bufs: &'b mut [&'a mut [u8]],
+}
+
+impl<'a, 'b> Scatter<'a, 'b> {
+    /// Wraps the list of slices `bufs`
+    pub fn new(bufs: &'b mut [&'a mut [u8]]) -> Self {
+        Self { bufs }
+    }
+}
+
+impl<'a, 'b> Write for Scatter<'a, 'b> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < data.len() {
+            match self.bufs.first_mut() {
+                None => break,
+                Some([]) => {
+                    let bufs = crate::lib::std::mem::take(&mut self.bufs);
+                    self.bufs = &mut bufs[1..];
+                }
+                Some(buf) => written += buf.write(&data[written..])?,
+            }
+        }
+
+        Ok(written)
+    }
+
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the given serializer `f` over the list of slices `bufs`, and returns the number
+/// of bytes written
+///
+/// When a slice is full, the output continues in the next one. See `Scatter` for the
+/// serializers that can be used.
+///
+/// ```rust
+/// use cookie_factory::{gen_multi_buffer, bytes::be_u16, combinator::string, sequence::pair};
+///
+/// let mut header = [0u8; 2];
+/// let mut payload = [0u8; 8];
+///
+/// let len = gen_multi_buffer(
+///   pair(be_u16(4), string("abcd")),
+///   &mut [&mut header[..], &mut payload[..]]
+/// ).unwrap();
+///
+/// assert_eq!(len, 6);
+/// assert_eq!(&header[..], &[0, 4][..]);
+/// assert_eq!(&payload[..4], &b"abcd"[..]);
+/// ```
+pub fn gen_multi_buffer<'a, 'b, F: SerializeFn<Scatter<'a, 'b>>>(
+    f: F,
+    bufs: &'b mut [&'a mut [u8]],
+) -> Result<usize, GenError> {
+    gen(f, Scatter::new(bufs)).map(|(_, len)| len as usize)
+}
+
+/// `Write` backend collecting the output as a list of slices, for vectored writes
+///
+/// Slices written through `combinator::slice_ref` and `combinator::string_ref` are
+/// stored by reference, without copying them, unless they are shorter than the threshold
+/// set with `with_copy_threshold`. All the other serializers go through
+/// `Write::write`, which can only copy: their output is appended to an owned buffer,
+/// consecutive writes sharing the same segment.
+///
+/// Use `gen_vectored` to run a serializer on it, then `io_slices` to get the list to
+/// pass to `Write::write_vectored`.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Vectored<'a> {
+    owned: Vec<u8>,
+    segments: Vec<Segment<'a>>,
+    // slices shorter than this are copied by `push_slice`
+    copy_threshold: usize,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+enum Segment<'a> {
+    Borrowed(&'a [u8]),
+    // range of the owned buffer
+    Owned(usize, usize),
+}
+
+#[cfg(feature = "std")]
+impl<'a> Vectored<'a> {
+    /// Creates an empty `Vectored`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty `Vectored` copying the slices shorter than `threshold` bytes
+    ///
+    /// Borrowing small slices, like short headers between large payloads, costs more in
+    /// `IoSlice` entries than copying them. With this constructor, `push_slice` only keeps
+    /// a reference to the slices of at least `threshold` bytes, and appends the other ones
+    /// to the owned buffer, merged with the neighbouring writes.
+    ///
+    /// ```rust
+    /// use cookie_factory::{gen_simple, Vectored, combinator::slice_ref, sequence::tuple};
+    ///
+    /// let payload = [0u8; 1024];
+    /// let v = gen_simple(
+    ///   tuple((slice_ref(&b"hd"[..]), slice_ref(&payload[..]), slice_ref(&b"end"[..]))),
+    ///   Vectored::with_copy_threshold(64),
+    /// ).unwrap();
+    /// let slices = v.io_slices();
+    ///
+    /// assert_eq!(slices.len(), 3);
+    /// assert_eq!(&slices[0][..], &b"hd"[..]);
+    /// assert_eq!(slices[1].as_ptr(), payload.as_ptr());
+    /// assert_eq!(&slices[2][..], &b"end"[..]);
+    /// ```
+    pub fn with_copy_threshold(threshold: usize) -> Self {
+        Vectored {
+            copy_threshold: threshold,
+            ..Self::default()
+        }
+    }
+
+    /// Appends `data` to the output without copying it, unless it is shorter than the
+    /// threshold given to `with_copy_threshold`
+    pub fn push_slice(&mut self, data: &'a [u8]) {
+        if data.len() < self.copy_threshold {
+            self.push_owned(data);
+        } else if !data.is_empty() {
+            self.segments.push(Segment::Borrowed(data));
+        }
+    }
+
+    fn push_owned(&mut self, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let start = self.owned.len();
+        self.owned.extend_from_slice(data);
+        let end = self.owned.len();
+
+        match self.segments.last_mut() {
+            Some(Segment::Owned(_, last_end)) if *last_end == start => *last_end = end,
+            _ => self.segments.push(Segment::Owned(start, end)),
+        }
+    }
+
+    /// Returns the output as a list of `IoSlice`, in the order it was written
+    pub fn io_slices(&self) -> Vec<std::io::IoSlice<'_>> {
+        self.segments
+            .iter()
+            .map(|segment| match *segment {
+                Segment::Borrowed(data) => std::io::IoSlice::new(data),
+                Segment::Owned(start, end) => std::io::IoSlice::new(&self.owned[start..end]),
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Write for Vectored<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.push_owned(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the given serializer `f` on a new `Vectored` and returns it
+///
+/// ```rust
+/// use cookie_factory::{gen_vectored, bytes::be_u16, combinator::slice_ref, sequence::tuple};
+///
+/// let payload = b"abcd";
+/// let v = gen_vectored(tuple((be_u16(4), slice_ref(&payload[..])))).unwrap();
+/// let slices = v.io_slices();
+///
+/// assert_eq!(slices.len(), 2);
+/// assert_eq!(&slices[0][..], &[0, 4][..]);
+/// assert_eq!(slices[1].as_ptr(), payload.as_ptr());
+///
+/// let mut out = Vec::new();
+/// std::io::Write::write_vectored(&mut out, &slices).unwrap();
+/// assert_eq!(&out[..], &b"\x00\x04abcd"[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_vectored<'a, F: SerializeFn<Vectored<'a>>>(f: F) -> Result<Vectored<'a>, GenError> {
+    gen_simple(f, Vectored::new())
+}
+
+/// Labels and byte ranges recorded by `gen_debug_trace`
+#[cfg(feature = "std")]
+pub type Trace = Vec<(&'static str, std::ops::Range<usize>)>;
+
+/// `Write` wrapper recording the byte ranges written by `combinator::named` serializers
+///
+/// Use `gen_debug_trace` or `gen_debug_tree` to run a serializer with it. It implements
+/// `Seek` if `inner` does, so that serializers like `length_value` work with it.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Traced<W> {
+    inner: W,
+    trace: Trace,
+    // nesting depth of each trace entry, and of the next one
+    depths: Vec<usize>,
+    depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W> Traced<W> {
+    /// Wraps `inner` with an empty trace
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            trace: Vec::new(),
+            depths: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Returns the wrapped `Write` and the recorded trace
+    pub fn into_inner(self) -> (W, Trace) {
+        (self.inner, self.trace)
+    }
+
+    /// Starts a trace entry at `start` and returns its index
+    pub(crate) fn open(&mut self, label: &'static str, start: usize) -> usize {
+        self.trace.push((label, start..start));
+        self.depths.push(self.depth);
+        self.depth += 1;
+        self.trace.len() - 1
+    }
+
+    /// Sets the end of the trace entry at `index`
+    pub(crate) fn close(&mut self, index: usize, end: usize) {
+        self.trace[index].1.end = end;
+        self.depth -= 1;
+    }
+
+    // Builds the trees of the entries at `depth` starting from `*index`, along with their
+    // children
+    fn tree(&self, index: &mut usize, depth: usize) -> Vec<TraceNode> {
+        let mut nodes = Vec::new();
+
+        while *index < self.trace.len() && self.depths[*index] == depth {
+            let (label, range) = self.trace[*index].clone();
+            *index += 1;
+            let children = self.tree(index, depth + 1);
+            nodes.push(TraceNode {
+                label,
+                range,
+                children,
+            });
+        }
+
+        nodes
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Traced<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Seek> io::Seek for Traced<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Byte range written by a `combinator::named` serializer, with the ranges of the `named`
+/// serializers it applied
+///
+/// `gen_debug_tree` returns them. The `Display` impl renders a node and its children as
+/// an indented list of labels and ranges.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+    /// Label given to `named`
+    pub label: &'static str,
+    /// Byte range written, relative to the start of the serialization
+    pub range: std::ops::Range<usize>,
+    /// Nodes of the `named` serializers applied inside this one, in order
+    pub children: Vec<TraceNode>,
+}
+
+#[cfg(feature = "std")]
+impl TraceNode {
+    fn render(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let label = format!("{:indent$}{}", "", self.label, indent = indent);
+        writeln!(f, "{} {:?}", label, self.range)?;
+        for child in &self.children {
+            child.render(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for TraceNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(f, 0)
+    }
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w`, and returns the byte range
+/// written by each `combinator::named` serializer along with the result
+///
+/// The ranges are relative to the start of the serialization, and listed in the order
+/// the serializers started, so an enclosing serializer comes before the ones it contains.
+///
+/// ```rust
+/// use cookie_factory::{gen_debug_trace, bytes::be_u32, combinator::{named, string}, sequence::pair};
+///
+/// let mut buf = [0u8; 100];
+///
+/// let (_, pos, trace) = gen_debug_trace(
+///   pair(named("length", be_u32(4)), named("data", string("abcd"))),
+///   &mut buf[..]
+/// ).unwrap();
+///
+/// assert_eq!(pos, 8);
+/// assert_eq!(trace, vec![("length", 0..4), ("data", 4..8)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_debug_trace<W: Write, F: SerializeFn<Traced<W>>>(
+    f: F,
+    w: W,
+) -> Result<(W, u64, Trace), GenError> {
+    let (traced, pos) = gen(f, Traced::new(w))?;
+    let (w, trace) = traced.into_inner();
+    Ok((w, pos, trace))
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w`, and returns the tree of the
+/// byte ranges written by the `combinator::named` serializers along with the result
+///
+/// This records the same ranges as `gen_debug_trace`, but each `named` serializer applied
+/// by another one is a child of it, which shows the structure of nested formats.
+///
+/// ```rust
+/// use cookie_factory::{gen_debug_tree, bytes::{be_u16, be_u8}, combinator::named, sequence::pair};
+///
+/// let (_, pos, tree) = gen_debug_tree(
+///   named("header", pair(named("kind", be_u8(1)), named("len", be_u16(0)))),
+///   Vec::new()
+/// ).unwrap();
+///
+/// assert_eq!(pos, 3);
+/// assert_eq!(tree.len(), 1);
+/// assert_eq!(tree[0].to_string(), "header 0..3\n  kind 0..1\n  len 1..3\n");
+/// ```
+#[cfg(feature = "std")]
+pub fn gen_debug_tree<W: Write, F: SerializeFn<Traced<W>>>(
+    f: F,
+    w: W,
+) -> Result<(W, u64, Vec<TraceNode>), GenError> {
+    let (traced, pos) = gen(f, Traced::new(w))?;
+    let tree = traced.tree(&mut 0, 0);
+    Ok((traced.inner, pos, tree))
+}
+
+/// Number of calls and bytes written recorded for a label by `gen_count_writes`
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CallStats {
+    /// Number of times the serializer was applied
+    pub calls: usize,
+    /// Total number of bytes written by the serializer
+    pub bytes: u64,
+}
+
+/// Statistics recorded by `gen_count_writes`, by label
+#[cfg(feature = "std")]
+pub type Profile = std::collections::BTreeMap<&'static str, CallStats>;
+
+/// `Write` wrapper counting the calls and output of `combinator::counted` serializers
+///
+/// Use `gen_count_writes` to run a serializer with it.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Profiled<W> {
+    inner: W,
+    profile: Profile,
+}
+
+#[cfg(feature = "std")]
+impl<W> Profiled<W> {
+    /// Wraps `inner` with an empty profile
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            profile: Profile::new(),
+        }
+    }
+
+    /// Returns the wrapped `Write` and the recorded profile
+    pub fn into_inner(self) -> (W, Profile) {
+        (self.inner, self.profile)
+    }
+
+    /// Adds a call that wrote `bytes` bytes to the statistics of `label`
+    pub(crate) fn record(&mut self, label: &'static str, bytes: u64) {
+        let stats = self.profile.entry(label).or_default();
+        stats.calls += 1;
+        stats.bytes += bytes;
     }
 }
 
-impl<W: Seek> io::Seek for WriteContext<W> {
-    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        let old_pos = self.write.seek(SeekFrom::Current(0))?;
-        let new_pos = self.write.seek(pos)?;
-        if new_pos >= old_pos {
-            self.position += new_pos - old_pos;
-        } else {
-            self.position -= old_pos - new_pos;
-        }
-        Ok(new_pos)
+#[cfg(feature = "std")]
+impl<W: Write> Write for Profiled<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
-/// Runs the given serializer `f` with the `Write` impl `w` and returns the result
+/// Runs the given serializer `f` with the `Write` impl `w`, and returns how many times each
+/// `combinator::counted` serializer was applied and how many bytes it wrote, along with
+/// the result
 ///
-/// This internally wraps `w` in a `WriteContext`, starting at position 0.
+/// Bytes written by nested `counted` serializers are counted for each of them.
 ///
 /// ```rust
-/// use cookie_factory::{gen, combinator::slice};
-///
-/// let mut buf = [0u8; 100];
+/// use cookie_factory::{gen_count_writes, combinator::{counted, string}, multi::all};
 ///
-/// {
-///   let (buf, pos) = gen(slice(&b"abcd"[..]), &mut buf[..]).unwrap();
-///   assert_eq!(buf.len(), 100 - 4);
-///   assert_eq!(pos, 4);
-/// }
+/// let words = ["ab", "cde"];
+/// let (_, pos, profile) = gen_count_writes(
+///   all(words.iter().map(|w| counted("word", string(w)))),
+///   Vec::new()
+/// ).unwrap();
 ///
-/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// assert_eq!(pos, 5);
+/// assert_eq!(profile["word"].calls, 2);
+/// assert_eq!(profile["word"].bytes, 5);
 /// ```
-pub fn gen<W: Write, F: SerializeFn<W>>(f: F, w: W) -> Result<(W, u64), GenError> {
-    f(WriteContext::from(w)).map(|ctx| ctx.into_inner())
+#[cfg(feature = "std")]
+pub fn gen_count_writes<W: Write, F: SerializeFn<Profiled<W>>>(
+    f: F,
+    w: W,
+) -> Result<(W, u64, Profile), GenError> {
+    let (profiled, pos) = gen(f, Profiled::new(w))?;
+    let (w, profile) = profiled.into_inner();
+    Ok((w, pos, profile))
 }
 
-/// Runs the given serializer `f` with the `Write` impl `w` and returns the updated `w`
+/// `Write` wrapper calling a callback with the number of bytes written, every time that
+/// number reaches a multiple of an interval
 ///
-/// This internally wraps `w` in a `WriteContext`, starting at position 0.
+/// Use `gen_with_progress` to run a serializer with it.
+pub struct Progress<W, C> {
+    inner: W,
+    written: u64,
+    interval: u64,
+    callback: C,
+}
+
+impl<W, C: FnMut(u64)> Progress<W, C> {
+    /// Wraps `inner`, calling `callback` every `interval` bytes
+    ///
+    /// Panics if `interval` is 0.
+    pub fn new(inner: W, interval: u64, callback: C) -> Self {
+        assert!(interval > 0, "Progress: interval must be at least 1");
+
+        Self {
+            inner,
+            written: 0,
+            interval,
+            callback,
+        }
+    }
+
+    /// Returns the wrapped `Write`
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write, C: FnMut(u64)> Write for Progress<W, C> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(data)?;
+        let before = self.written / self.interval;
+        self.written += n as u64;
+        if self.written / self.interval > before {
+            (self.callback)(self.written);
+        }
+        Ok(n)
+    }
+
+    #[cfg(feature = "std")]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Runs the given serializer `f` with the `Write` impl `w`, calling `callback` with the
+/// number of bytes written every `interval` bytes
+///
+/// The callback is called at most once per call to `write`, after the write that crossed
+/// one or more multiples of `interval`, so the count it gets can be past that multiple.
+///
+/// Panics if `interval` is 0.
 ///
 /// ```rust
-/// use cookie_factory::{gen_simple, combinator::slice};
+/// use cookie_factory::{gen_with_progress, combinator::string};
 ///
 /// let mut buf = [0u8; 100];
+/// let mut calls = Vec::new();
 ///
-/// {
-///   let buf = gen_simple(slice(&b"abcd"[..]), &mut buf[..]).unwrap();
-///   assert_eq!(buf.len(), 100 - 4);
-/// }
+/// let (_, pos) = gen_with_progress(
+///   string("abcdefghij"),
+///   &mut buf[..],
+///   4,
+///   |written| calls.push(written),
+/// ).unwrap();
 ///
-/// assert_eq!(&buf[..4], &b"abcd"[..]);
+/// assert_eq!(pos, 10);
+/// assert_eq!(calls, vec![10]);
 /// ```
-pub fn gen_simple<W: Write, F: SerializeFn<W>>(f: F, w: W) -> Result<W, GenError> {
-    f(WriteContext::from(w)).map(|ctx| ctx.into_inner().0)
+pub fn gen_with_progress<W: Write, C: FnMut(u64), F: SerializeFn<Progress<W, C>>>(
+    f: F,
+    w: W,
+    interval: u64,
+    callback: C,
+) -> Result<(W, u64), GenError> {
+    let (progress, pos) = gen(f, Progress::new(w, interval, callback))?;
+    Ok((progress.into_inner(), pos))
 }
 
 /// Trait for `Write` types that allow skipping over the data
@@ -166,6 +1854,8 @@ pub trait BackToTheBuffer: Write {
 /// Trait for `Seek` types that want to automatically implement `BackToTheBuffer`
 pub trait Seek: Write + io::Seek {}
 impl Seek for io::Cursor<&mut [u8]> {}
+#[cfg(feature = "std")]
+impl<W: Seek> Seek for Traced<W> {}
 
 impl<W: Seek> BackToTheBuffer for W {
     fn reserve_write_use<
@@ -309,3 +1999,829 @@ impl BackToTheBuffer for Vec<u8> {
         Ok(new_context)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gen_array() {
+        use crate::bytes::{be_u16, be_u32, be_u64};
+        use crate::sequence::pair;
+
+        let buf: [u8; 8] = gen_array(be_u64(0x0102_0304_0506_0708)).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let res = gen_array::<8, _>(pair(be_u32(1), be_u16(2)));
+        assert!(matches!(res, Err(GenError::BufferTooBig(2))));
+
+        let res = gen_array::<4, _>(be_u64(1));
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+    }
+
+    #[test]
+    fn test_gen_reserve_then_fill() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::combinator::string;
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 16];
+        {
+            let (reserved, rest) = gen_reserve_then_fill(&mut buf[..], 4).unwrap();
+            assert_eq!(reserved.len(), 4);
+            assert_eq!(rest.len(), 12);
+
+            let (_, len) = gen(pair(string("body"), be_u8(0)), rest).unwrap();
+            let header = gen_simple(be_u32(len as u32), reserved).unwrap();
+            assert!(header.is_empty());
+        }
+        assert_eq!(&buf[..9], &b"\x00\x00\x00\x05body\x00"[..]);
+
+        let res = gen_reserve_then_fill(&mut buf[..], 20);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_error_is_io_error() {
+        use crate::combinator::string;
+        use std::io::ErrorKind;
+
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(ErrorKind::BrokenPipe, "broken pipe"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let res = gen_simple(string("abcd"), FailingWriter);
+        assert!(matches!(res, Err(GenError::IoError(e)) if e.kind() == ErrorKind::BrokenPipe));
+
+        let err: GenError = io::Error::from(ErrorKind::UnexpectedEof).into();
+        assert!(matches!(err, GenError::IoError(_)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_iter_concat() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::combinator::string;
+        use crate::multi::all;
+        use crate::sequence::tuple;
+
+        fn serializer<'a, W: Write + 'a>(words: &'a [&'a str]) -> impl SerializeFn<W> + 'a {
+            tuple((be_u32(0xdead_beef), all(words.iter().map(string)), be_u8(0)))
+        }
+
+        let words = ["lorem", "ipsum", "dolor", "sit", "amet"];
+        let full = gen_simple(serializer(&words), Vec::new()).unwrap();
+
+        let mut concatenated = Vec::new();
+        let mut count = 0;
+        for chunk in gen_iter(serializer(&words), 4) {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() <= 4);
+            concatenated.extend(chunk);
+            count += 1;
+        }
+
+        assert_eq!(concatenated, full);
+        assert_eq!(count, full.chunks(4).count());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_iter_empty() {
+        use crate::combinator::string;
+
+        assert_eq!(gen_iter(string(""), 4).count(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_iter_error() {
+        use crate::combinator::string;
+        use crate::sequence::pair;
+
+        let failing = pair(string("abcd"), |_| Err(GenError::NotYetImplemented));
+        let mut chunks = gen_iter(failing, 2);
+        assert!(matches!(
+            chunks.next(),
+            Some(Err(GenError::NotYetImplemented))
+        ));
+        assert!(chunks.next().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_iter_stateful() {
+        use crate::combinator::copy_reader;
+        use std::io::Cursor;
+
+        // the reader is only read once, by a single run of the serializer
+        let data: Vec<u8> = (0..20).collect();
+        let chunks: Vec<Vec<u8>> = gen_iter(copy_reader(Cursor::new(&data), 20), 8)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_with_scratch() {
+        use crate::bytes::be_u8;
+        use crate::combinator::{base64, slice};
+        use crate::sequence::pair;
+
+        let mut scratch = Vec::new();
+        let mut buf = [0u8; 8];
+        gen_with_scratch(slice(&b"abcdef"[..]), &mut scratch, base64, &mut buf[..]).unwrap();
+        assert_eq!(&buf, b"YWJjZGVm");
+        let capacity = scratch.capacity();
+        let pointer = scratch.as_ptr();
+        assert!(capacity > 0);
+
+        let mut buf = [0u8; 4];
+        gen_with_scratch(slice(&b"ab"[..]), &mut scratch, base64, &mut buf[..]).unwrap();
+        assert_eq!(&buf, b"YWI=");
+        assert_eq!(scratch.capacity(), capacity);
+        assert_eq!(scratch.as_ptr(), pointer);
+
+        // the scratch buffer keeps its allocation when the body fails
+        let failing = pair(be_u8(1), |_| Err(GenError::NotYetImplemented));
+        let res = gen_with_scratch(failing, &mut scratch, base64, &mut buf[..]);
+        assert!(matches!(res, Err(GenError::NotYetImplemented)));
+        assert_eq!(scratch.capacity(), capacity);
+        assert_eq!(scratch.as_ptr(), pointer);
+    }
+
+    #[test]
+    fn test_gen_partial() {
+        use crate::bytes::be_u32;
+        use crate::lib::std::cell::Cell;
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 8];
+        let written = Cell::new(0);
+        let res = gen_partial(
+            pair(be_u32(0x0102_0304), |_| Err(GenError::NotYetImplemented)),
+            &mut buf[..],
+            &written,
+        );
+        assert_eq!(written.get(), 4);
+        assert!(matches!(res, Err(GenError::NotYetImplemented)));
+        assert_eq!(&buf[..4], &[1, 2, 3, 4]);
+
+        let written = Cell::new(3);
+        let res = gen_partial(pair(be_u32(1), be_u32(2)), &mut buf[..], &written);
+        assert_eq!(written.get(), 8);
+        assert!(res.is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_partial_resume() {
+        use crate::bytes::{be_u16, be_u64};
+        use crate::combinator::{slice, string};
+        use crate::multi::all;
+        use crate::sequence::tuple;
+
+        fn serializer<'a, W: Write + 'a>(values: &'a [u64]) -> impl SerializeFn<W> + 'a {
+            tuple((
+                string("header: "),
+                be_u16(0x0102),
+                all(values.iter().map(|v| be_u64(*v))),
+                slice(&b"trailing bytes"[..]),
+                string("!!"),
+            ))
+        }
+
+        let values = [1u64, 2, 3];
+        let expected = gen_simple(serializer(&values), Vec::new()).unwrap();
+        assert_eq!(expected.len(), 50);
+
+        let mut state = gen_partial_resume(serializer(&values));
+        let mut out = Vec::new();
+        for _ in 0..5 {
+            let mut buf = [0u8; 10];
+            assert_eq!(state.fill(&mut buf[..]).unwrap(), 10);
+            out.extend_from_slice(&buf);
+        }
+        assert_eq!(out, expected);
+        assert_eq!(state.position(), 50);
+        assert!(state.is_done());
+
+        let mut buf = [0u8; 10];
+        assert_eq!(state.fill(&mut buf[..]).unwrap(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_partial_resume_stateful() {
+        use crate::bytes::be_u8;
+        use crate::combinator::{dictionary_entry, slice, Dictionary};
+        use crate::sequence::pair;
+
+        // the second entry is a reference to the first one, since they are only applied once
+        let dictionary = Dictionary::new();
+        let entry = || {
+            dictionary_entry(
+                &dictionary,
+                &b"abc"[..],
+                |data: &[u8]| slice(data.to_vec()),
+                |position| pair(be_u8(0x80), be_u8(position as u8)),
+            )
+        };
+        let mut state = gen_partial_resume(pair(entry(), entry()));
+
+        let mut out = Vec::new();
+        while !state.is_done() {
+            let mut buf = [0u8; 2];
+            let written = state.fill(&mut buf[..]).unwrap();
+            out.extend_from_slice(&buf[..written]);
+        }
+        assert_eq!(out, b"abc\x80\x00");
+        assert_eq!(dictionary.len(), 1);
+
+        let mut state = gen_partial_resume(|_| Err(GenError::NotYetImplemented));
+        let mut buf = [0u8; 2];
+        assert!(matches!(
+            state.fill(&mut buf[..]),
+            Err(GenError::NotYetImplemented)
+        ));
+        assert!(state.is_done());
+        assert_eq!(state.fill(&mut buf[..]).unwrap(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_gen_streaming_async() {
+        use crate::bytes::be_u32;
+        use crate::combinator::copy_reader;
+        use crate::sequence::pair;
+        use std::future::Future;
+        use std::io::Cursor;
+        use std::pin::Pin;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        // in memory writer accepting at most 100 bytes per call, and only every other call
+        struct SlowWriter {
+            data: Vec<u8>,
+            ready: bool,
+            flushed: bool,
+        }
+
+        impl futures_io::AsyncWrite for SlowWriter {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                self.ready = !self.ready;
+                if !self.ready {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                let n = std::cmp::min(buf.len(), 100);
+                self.data.extend_from_slice(&buf[..n]);
+                Poll::Ready(Ok(n))
+            }
+
+            fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                self.flushed = true;
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        fn block_on<T>(future: impl Future<Output = T>) -> T {
+            let waker = Waker::from(Arc::new(NoopWaker));
+            let mut cx = Context::from_waker(&waker);
+            let mut future = Box::pin(future);
+            loop {
+                if let Poll::Ready(res) = future.as_mut().poll(&mut cx) {
+                    return res;
+                }
+            }
+        }
+
+        // `copy_reader` consumes its reader, so it must only be applied once
+        let payload: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let serializer = pair(
+            be_u32(payload.len() as u32),
+            copy_reader(Cursor::new(&payload), payload.len()),
+        );
+
+        let mut writer = SlowWriter {
+            data: Vec::new(),
+            ready: false,
+            flushed: false,
+        };
+        let written = block_on(gen_streaming_async(serializer, 4096, &mut writer)).unwrap();
+
+        assert_eq!(written, 100_004);
+        assert_eq!(writer.data[..4], 100_000u32.to_be_bytes());
+        assert_eq!(&writer.data[4..], &payload[..]);
+        assert!(writer.flushed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_to_writer() {
+        use crate::bytes::be_u32;
+        use crate::combinator::string;
+        use crate::sequence::pair;
+
+        let mut out: Vec<u8> = b"xy".to_vec();
+        let len = gen_to_writer(pair(be_u32(3), string("abc")), &mut out).unwrap();
+        assert_eq!(len, 7);
+        assert_eq!(out.len(), 2 + len);
+        assert_eq!(&out[..], b"xy\x00\x00\x00\x03abc");
+
+        let mut buf = [0u8; 4];
+        let mut writer = &mut buf[..];
+        let res = gen_to_writer(string("abcdef"), &mut writer);
+        assert!(matches!(
+            res,
+            Err(GenError::IoError(e)) if e.kind() == std::io::ErrorKind::WriteZero
+        ));
+    }
+
+    #[test]
+    fn test_gen_at() {
+        use crate::bytes::be_u32;
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 32];
+        let serializer = pair(be_u32(0x0102_0304), be_u32(0x0506_0708));
+        let end = gen_at(serializer, &mut buf[..], 8).unwrap();
+        assert_eq!(end, 16);
+        assert_eq!(&buf[..8], &[0; 8]);
+        assert_eq!(&buf[8..16], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(&buf[16..], &[0; 16]);
+
+        assert_eq!(gen_at(be_u32(0), &mut buf[..], 28).unwrap(), 32);
+
+        let res = gen_at(be_u32(0), &mut buf[..], 33);
+        assert!(matches!(res, Err(GenError::InvalidOffset)));
+        let res = gen_at(be_u32(0), &mut buf[..], 32);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(4))));
+
+        let res = gen_at(be_u32(0), &mut buf[..], 30);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_into_uninit() {
+        use crate::bytes::be_u32;
+        use crate::combinator::string;
+        use crate::sequence::pair;
+
+        let mut v: Vec<u8> = Vec::with_capacity(8);
+        v.push(0xff);
+
+        let len = gen_into_uninit(
+            pair(be_u32(0x0102_0304), string("ab")),
+            v.spare_capacity_mut(),
+        )
+        .unwrap();
+        assert_eq!(len, 6);
+        // SAFETY: the first `len` bytes of the spare capacity were initialized
+        unsafe { v.set_len(1 + len) };
+        assert_eq!(&v[..], &[0xff, 1, 2, 3, 4, b'a', b'b']);
+
+        let mut buf = [crate::lib::std::mem::MaybeUninit::<u8>::uninit(); 3];
+        let res = gen_into_uninit(be_u32(1), &mut buf[..]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_gen_multi_buffer() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::sequence::pair;
+
+        let mut a = [0u8; 2];
+        let mut b = [0u8; 0];
+        let mut c = [0u8; 4];
+        let len = gen_multi_buffer(
+            pair(be_u8(0xff), be_u32(0x0102_0304)),
+            &mut [&mut a[..], &mut b[..], &mut c[..]],
+        )
+        .unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(a, [0xff, 0x01]);
+        assert_eq!(c, [0x02, 0x03, 0x04, 0x00]);
+
+        let mut a = [0u8; 2];
+        let mut c = [0u8; 4];
+        let res = gen_multi_buffer(pair(be_u32(1), be_u32(2)), &mut [&mut a[..], &mut c[..]]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_into_cursor() {
+        use crate::bytes::{be_u16, be_u8};
+        use crate::lib::std::io::{Cursor, Write};
+        use crate::sequence::pair;
+
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_all(b"head").unwrap();
+
+        let position = gen_into_cursor(pair(be_u8(1), be_u16(0x0203)), &mut cursor).unwrap();
+        assert_eq!(position, 7);
+        cursor.write_all(b"tail").unwrap();
+        assert_eq!(&cursor.get_ref()[..], &b"head\x01\x02\x03tail"[..]);
+
+        // writing in the middle overwrites the following bytes
+        cursor.set_position(5);
+        let position = gen_into_cursor(be_u8(0xff), &mut cursor).unwrap();
+        assert_eq!(position, 6);
+        assert_eq!(&cursor.get_ref()[..], &b"head\x01\xff\x03tail"[..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_with_backrefs() {
+        use crate::bytes::be_u8;
+        use crate::combinator::{pointer_be_u32, pointer_target, slice, Pointers};
+        use crate::sequence::tuple;
+
+        // a header pointing to a section following a variable length body
+        let pointers = Pointers::new();
+        let body = [7u8; 5];
+        let buf = gen_with_backrefs(
+            tuple((
+                be_u8(1),
+                pointer_be_u32(&pointers, "section"),
+                slice(&body[..]),
+                pointer_target(&pointers, "section"),
+                be_u8(2),
+            )),
+            &pointers,
+        )
+        .unwrap();
+        assert_eq!(
+            &buf[..],
+            &b"\x01\x00\x00\x00\x0a\x07\x07\x07\x07\x07\x02"[..]
+        );
+
+        let pointers = Pointers::new();
+        let res = gen_with_backrefs(pointer_be_u32(&pointers, "none"), &pointers);
+        assert!(matches!(res, Err(GenError::InvalidValue)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_serializer_reuse() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::combinator::string;
+        use crate::sequence::tuple;
+
+        let mut serializer = Serializer::new();
+        let first = serializer
+            .serialize(tuple((be_u8(1), be_u32(2), string("first"))))
+            .unwrap()
+            .to_vec();
+        assert_eq!(&first[..], &b"\x01\x00\x00\x00\x02first"[..]);
+
+        let capacity = serializer.capacity();
+        let ptr = serializer.buf.as_ptr();
+        let second = serializer
+            .serialize(tuple((be_u8(3), string("two"))))
+            .unwrap();
+        assert_eq!(second, &b"\x03two"[..]);
+        assert_eq!(second.as_ptr(), ptr);
+        assert_eq!(serializer.capacity(), capacity);
+
+        // a failed message does not leak into the next one
+        let res = serializer.serialize(tuple((be_u8(9), crate::bytes::nibbles(0x10, 0))));
+        assert!(res.is_err());
+        assert_eq!(serializer.serialize(be_u8(4)).unwrap(), &[4][..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_debug_tree() {
+        use crate::bytes::{be_u16, be_u8};
+        use crate::combinator::{envelope, length_value, named, string};
+        use crate::sequence::tuple;
+
+        let mut buf = [0u8; 16];
+        let (_, pos, tree) = gen_debug_tree(
+            named(
+                "envelope",
+                envelope(
+                    be_u8(0x7e),
+                    2,
+                    |len| be_u16(len as u16),
+                    named(
+                        "record",
+                        length_value(tuple((
+                            named("id", be_u8(1)),
+                            named("name", string("ab")),
+                            named("empty", string("")),
+                        ))),
+                    ),
+                ),
+            ),
+            io::Cursor::new(&mut buf[..]),
+        )
+        .unwrap();
+        assert_eq!(pos, 11);
+        assert_eq!(&buf[..11], &b"\x7e\x00\x07\x00\x00\x00\x03\x01ab\x7e"[..]);
+
+        let leaf = |label, range| TraceNode {
+            label,
+            range,
+            children: Vec::new(),
+        };
+        let expected = TraceNode {
+            label: "envelope",
+            range: 0..11,
+            children: vec![TraceNode {
+                label: "record",
+                range: 3..10,
+                children: vec![leaf("id", 7..8), leaf("name", 8..10), leaf("empty", 10..10)],
+            }],
+        };
+        assert_eq!(tree, vec![expected]);
+        assert_eq!(
+            tree[0].to_string(),
+            "envelope 0..11\n  record 3..10\n    id 7..8\n    name 8..10\n    empty 10..10\n"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_aligned() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::sequence::pair;
+
+        let buf = gen_aligned(pair(be_u32(0x0102_0304), be_u8(5)), 8).unwrap();
+        assert_eq!(buf, vec![1, 2, 3, 4, 5, 0, 0, 0]);
+
+        let buf = gen_aligned(be_u32(1), 4).unwrap();
+        assert_eq!(buf, vec![0, 0, 0, 1]);
+
+        let buf = gen_aligned(be_u8(1), 1).unwrap();
+        assert_eq!(buf, vec![1]);
+    }
+
+    #[test]
+    fn test_gen_checked_capacity() {
+        use crate::combinator::slice;
+        use crate::sequence::tuple;
+
+        let data = [7u8; 100];
+        let mut buf = [0u8; 50];
+
+        // the second field is the one which does not fit
+        let res = gen(
+            tuple((slice(&data[..30]), slice(&data[30..]))),
+            &mut buf[..],
+        );
+        assert!(matches!(res, Err(GenError::BufferTooSmall(50))));
+        let res = gen_checked_capacity!(
+            tuple((slice(&data[..30]), slice(&data[30..]))),
+            &mut buf[..]
+        );
+        assert!(matches!(
+            res,
+            Err(GenError::CapacityRequired {
+                required: 100,
+                available: 50,
+            })
+        ));
+
+        let mut buf = [0u8; 100];
+        let (out, pos) = gen_checked_capacity!(
+            tuple((slice(&data[..30]), slice(&data[30..]))),
+            &mut buf[..]
+        )
+        .unwrap();
+        assert_eq!(pos, 100);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_gen_expect() {
+        use crate::bytes::{be_u16, be_u32};
+        use crate::sequence::pair;
+
+        let mut buf = [0u8; 8];
+        let (out, pos) = gen_expect(pair(be_u16(1), be_u32(2)), &mut buf[..], 6).unwrap();
+        assert_eq!(pos, 6);
+        assert_eq!(out.len(), 2);
+        assert_eq!(&buf[..6], &[0, 1, 0, 0, 0, 2]);
+
+        let res = gen_expect(pair(be_u16(1), be_u16(2)), &mut buf[..], 6);
+        assert!(matches!(
+            res,
+            Err(GenError::LengthMismatch {
+                expected: 6,
+                actual: 4,
+            })
+        ));
+
+        let res = gen_expect(be_u32(1), &mut buf[..2], 4);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(2))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_to_hex() {
+        use crate::bytes::{be_u32, le_u16};
+        use crate::combinator::string;
+        use crate::sequence::tuple;
+
+        let serializer = tuple((be_u32(0xdead_beef), le_u16(0x0a0b), string("Az")));
+        assert_eq!(gen_to_hex(serializer).unwrap(), "deadbeef0b0a417a");
+        assert_eq!(gen_to_hex(string("")).unwrap(), "");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_segmented() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::combinator::string;
+        use crate::multi::all;
+        use crate::sequence::tuple;
+
+        let records = ["first", "second", "third"];
+        let header = |total: u64| tuple((string("SEG"), be_u8(1), be_u32(total as u32)));
+        let body = all(records
+            .iter()
+            .map(|r| tuple((be_u8(r.len() as u8), string(r)))));
+
+        let buf = gen_segmented(header, body).unwrap();
+        assert_eq!(buf.len(), 8 + 19);
+        assert_eq!(&buf[..8], &b"SEG\x01\x00\x00\x00\x1b"[..]);
+        assert_eq!(&buf[8..], &b"\x05first\x06second\x05third"[..]);
+
+        let growing = |total: u64| crate::combinator::cond(total > 0, be_u8(0));
+        let res = gen_segmented(growing, string("abc"));
+        assert!(matches!(
+            res,
+            Err(GenError::LengthMismatch {
+                expected: 0,
+                actual: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_gen_with_progress() {
+        use crate::bytes::be_u8;
+        use crate::multi::many_ref;
+
+        let mut buf = [0u8; 10_000];
+        let mut count = 0;
+        let mut last = 0;
+        let serializer = many_ref(0..10_000u32, |i| be_u8(i as u8));
+        let (out, pos) = gen_with_progress(serializer, &mut buf[..], 1000, |written| {
+            count += 1;
+            assert_eq!(written, last + 1000);
+            last = written;
+        })
+        .unwrap();
+        assert_eq!(pos, 10_000);
+        assert!(out.is_empty());
+        assert_eq!(count, 10);
+        assert_eq!(last, 10_000);
+    }
+
+    #[test]
+    fn test_gen_measured() {
+        use crate::bytes::be_u8;
+        use crate::combinator::string;
+        use crate::multi::all;
+        use crate::sequence::pair;
+
+        let words = ["a", "bcd", "", "efghij"];
+        let mut buf = [0u8; 16];
+        let (rest, written) = gen_measured(
+            all(words.iter().map(|w| pair(be_u8(w.len() as u8), string(w)))),
+            &mut buf[..],
+        )
+        .unwrap();
+        assert_eq!(written, 14);
+        assert_eq!(rest.len(), 16 - 14);
+        assert_eq!(&buf[..written], &b"\x01a\x03bcd\x00\x06efghij"[..]);
+
+        let res = gen_measured(string("abc"), &mut buf[..2]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_gen_ref() {
+        use crate::bytes::{be_u16, be_u8};
+        use crate::combinator::skip;
+        use crate::sequence::tuple;
+
+        let mut buf = [0xffu8; 8];
+        let written = gen_ref(tuple((be_u8(1), skip(2), be_u16(0x0203))), &mut buf[..]).unwrap();
+        assert_eq!(written.len(), 5);
+        assert_eq!(written, &[1, 0xff, 0xff, 2, 3][..]);
+
+        let written = gen_ref(skip(0), &mut buf[..]).unwrap();
+        assert!(written.is_empty());
+
+        let res = gen_ref(be_u16(1), &mut buf[..1]);
+        assert!(matches!(res, Err(GenError::BufferTooSmall(1))));
+    }
+
+    #[test]
+    fn test_gen_deterministic() {
+        use crate::bytes::be_u16;
+        use crate::combinator::skip;
+        use crate::sequence::tuple;
+
+        fn serializer<'a>() -> impl SerializeFn<&'a mut [u8]> {
+            tuple((be_u16(0x0102), skip(3), be_u16(0x0304)))
+        }
+
+        let mut buf = [0xaau8; 8];
+        let (_, pos) = gen(serializer(), &mut buf[..]).unwrap();
+        assert_eq!(pos, 7);
+        assert_eq!(buf, [1, 2, 0xaa, 0xaa, 0xaa, 3, 4, 0xaa]);
+
+        let mut buf = [0xaau8; 8];
+        let (out, pos) = gen_deterministic(serializer(), &mut buf[..]).unwrap();
+        assert_eq!(pos, 7);
+        assert_eq!(out.len(), 1);
+        assert_eq!(buf, [1, 2, 0, 0, 0, 3, 4, 0]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_split_header_body() {
+        use crate::bytes::{be_u32, be_u8};
+        use crate::combinator::string;
+        use crate::multi::separated_list;
+        use crate::sequence::pair;
+
+        let items = ["one", "two", "three"];
+        let (header, body) = gen_split_header_body(
+            |len| pair(be_u8(1), be_u32(len as u32)),
+            separated_list(string(","), items.iter().map(string)),
+        )
+        .unwrap();
+
+        assert_eq!(&body[..], &b"one,two,three"[..]);
+        assert_eq!(&header[..], &[1, 0, 0, 0, 13][..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gen_with_max_size() {
+        use crate::bytes::{be_u8, Be, Le};
+        use crate::combinator::Base64;
+
+        let data = [0xa5u8; 10];
+
+        let buf = gen_with_max_size(Base64(&data[..])).unwrap();
+        assert_eq!(buf.len(), 16);
+        assert_eq!(buf.capacity(), 16);
+
+        let buf = gen_with_max_size((Be(1u32), Base64(&data[..]), Le(2u64))).unwrap();
+        assert_eq!(buf.len(), 28);
+        assert_eq!(buf.capacity(), 28);
+        assert_eq!(&buf[..4], &[0, 0, 0, 1][..]);
+        assert_eq!(&buf[20..], &[2, 0, 0, 0, 0, 0, 0, 0][..]);
+
+        // a wrong bound is reported rather than silently reallocating
+        struct TwoBytes;
+
+        impl MaxSize<Vec<u8>> for TwoBytes {
+            fn max_size(&self) -> usize {
+                1
+            }
+
+            fn serialize(&self, w: WriteContext<Vec<u8>>) -> GenResult<Vec<u8>> {
+                be_u8(1)(be_u8(0)(w)?)
+            }
+        }
+
+        let res = gen_with_max_size(TwoBytes);
+        assert!(matches!(res, Err(GenError::LimitExceeded(1))));
+    }
+}
@@ -29,12 +29,12 @@ pub trait Seek {
 // also works in non-std environments
 pub struct Cursor<T>(T, u64);
 
-impl<'a> Cursor<&'a mut [u8]> {
-    pub fn new(inner: &'a mut [u8]) -> Self {
+impl<T> Cursor<T> {
+    pub fn new(inner: T) -> Self {
         Self(inner, 0)
     }
 
-    pub fn into_inner(self) -> &'a mut [u8] {
+    pub fn into_inner(self) -> T {
         self.0
     }
 
@@ -46,12 +46,12 @@ impl<'a> Cursor<&'a mut [u8]> {
         self.1 = pos;
     }
 
-    pub fn get_mut(&mut self) -> &mut [u8] {
-        self.0
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.0
     }
 
-    pub fn get_ref(&self) -> &[u8] {
-        self.0
+    pub fn get_ref(&self) -> &T {
+        &self.0
     }
 }
 
@@ -64,6 +64,16 @@ impl<'a> Write for Cursor<&'a mut [u8]> {
     }
 }
 
+impl<const N: usize> Write for Cursor<[u8; N]> {
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        let start = core::cmp::min(self.1 as usize, N);
+        let amt = (&mut self.0[start..]).write(data)?;
+        self.1 += amt as u64;
+
+        Ok(amt)
+    }
+}
+
 impl<'a> Seek for Cursor<&'a mut [u8]> {
     fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
         let (start, offset) = match pos {
@@ -19,12 +19,12 @@ pub mod lib {
         pub mod io {
             pub use std::io::{Cursor, Error, Result, Seek, SeekFrom, Write};
         }
-        pub use std::{cmp, fmt, iter, mem, result, slice};
+        pub use std::{cell, cmp, fmt, iter, mem, ptr, result, slice, time};
     }
 
     #[cfg(not(feature = "std"))]
     pub mod std {
-        pub use core::{cmp, iter, mem, result, slice};
+        pub use core::{cell, cmp, iter, mem, ptr, result, slice, time};
         #[macro_use]
         pub use core::fmt;
 
@@ -39,6 +39,7 @@ pub mod gen;
 
 mod internal;
 pub use internal::*;
+pub mod bits;
 pub mod bytes;
 pub mod combinator;
 pub mod multi;
@@ -1,4 +1,6 @@
 //! serializers working on a list of elements (vectors, iterators, etc)
+#[cfg(feature = "std")]
+use crate::internal::{gen_simple, SerializeFnV};
 use crate::internal::{SerializeFn, WriteContext};
 use crate::lib::std::io::Write;
 
@@ -34,6 +36,54 @@ where
     }
 }
 
+/// Applies an iterator of serializers of the same type, stopping before the first one that
+/// would make the output longer than `max` bytes
+///
+/// The returned value is true if some serializers were left out. Only whole elements are
+/// written: each one is first serialized to a temporary `Vec<u8>`, then copied if it fits
+/// in what is left of `max`. The number of bytes written is the progress of the position.
+/// Running out of space in the output is still an error, `max` is not checked against it.
+///
+/// ```rust
+/// use cookie_factory::{WriteContext, multi::truncated_list, combinator::string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// let data = vec!["abcd", "efgh", "ijkl"];
+/// {
+///   let (out, truncated) = truncated_list(10, data.iter().map(string))(WriteContext::from(&mut buf[..])).unwrap();
+///   assert_eq!(out.position, 8);
+///   assert!(truncated);
+/// }
+///
+/// assert_eq!(&buf[..8], &b"abcdefgh"[..]);
+///
+/// let (out, truncated) = truncated_list(12, data.iter().map(string))(WriteContext::from(&mut buf[..])).unwrap();
+/// assert_eq!(out.position, 12);
+/// assert!(!truncated);
+/// ```
+#[cfg(feature = "std")]
+pub fn truncated_list<G, W: Write, It>(max: usize, values: It) -> impl SerializeFnV<W, bool>
+where
+    G: SerializeFn<Vec<u8>>,
+    It: Clone + Iterator<Item = G>,
+{
+    move |mut out: WriteContext<W>| {
+        let mut remaining = max;
+
+        for v in values.clone() {
+            let data = gen_simple(v, Vec::new())?;
+            if data.len() > remaining {
+                return Ok((out, true));
+            }
+            remaining -= data.len();
+            out = crate::combinator::slice(&data[..])(out)?;
+        }
+
+        Ok((out, false))
+    }
+}
+
 /// Applies an iterator of serializers of the same type with a separator between each serializer
 ///
 /// ```rust
@@ -105,3 +155,190 @@ where
         Ok(out)
     }
 }
+
+/// Applies the serializers generated by `f` for each index from 0 to `n - 1`
+///
+/// ```rust
+/// use cookie_factory::{gen, multi::repeat_indexed, bytes::be_u8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(repeat_indexed(4, |i| be_u8(i as u8)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 4);
+///   assert_eq!(buf.len(), 100 - 4);
+/// }
+///
+/// assert_eq!(&buf[..4], &[0u8, 1u8, 2u8, 3u8][..]);
+/// ```
+pub fn repeat_indexed<F, G, W: Write>(n: usize, f: F) -> impl SerializeFn<W>
+where
+    F: Fn(usize) -> G,
+    G: SerializeFn<W>,
+{
+    move |mut out: WriteContext<W>| {
+        for i in 0..n {
+            out = f(i)(out)?;
+        }
+        Ok(out)
+    }
+}
+
+/// Applies the serializers generated by `f` for each of the `N` elements of `items`
+///
+/// Unlike the combinators taking an iterator, the number of elements is part of the type,
+/// so a fixed size field of the format cannot get the wrong number of elements.
+///
+/// ```rust
+/// use cookie_factory::{gen, multi::array_of, bytes::be_u16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(array_of([1u16, 2, 3, 0xffff], |v| be_u16(*v)), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 8);
+///   assert_eq!(buf.len(), 100 - 8);
+/// }
+///
+/// assert_eq!(&buf[..8], &[0u8, 1u8, 0u8, 2u8, 0u8, 3u8, 0xffu8, 0xffu8][..]);
+/// ```
+pub fn array_of<const N: usize, T, F, G, W: Write>(items: [T; N], f: F) -> impl SerializeFn<W>
+where
+    F: Fn(&T) -> G,
+    G: SerializeFn<W>,
+{
+    move |mut out: WriteContext<W>| {
+        for item in items.iter() {
+            out = f(item)(out)?;
+        }
+        Ok(out)
+    }
+}
+
+/// Applies the serializers generated by `f` for each index from 0 to 255
+///
+/// This is `repeat_indexed(256, ..)` with the index as an `u8`, for the fixed 256 entry
+/// tables of some formats, like palettes or translation tables.
+///
+/// ```rust
+/// use cookie_factory::{gen, multi::table_256, bytes::be_u8, sequence::tuple};
+///
+/// let mut buf = [0u8; 1000];
+///
+/// // a grayscale palette, with one RGB entry per level
+/// {
+///   let (buf, pos) = gen(table_256(|i| tuple((be_u8(i), be_u8(i), be_u8(i)))), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 768);
+///   assert_eq!(buf.len(), 1000 - 768);
+/// }
+///
+/// assert_eq!(&buf[..6], &[0u8, 0u8, 0u8, 1u8, 1u8, 1u8][..]);
+/// assert_eq!(&buf[765..768], &[255u8, 255u8, 255u8][..]);
+/// ```
+pub fn table_256<F, G, W: Write>(f: F) -> impl SerializeFn<W>
+where
+    F: Fn(u8) -> G,
+    G: SerializeFn<W>,
+{
+    move |mut out: WriteContext<W>| {
+        for i in 0..=u8::MAX {
+            out = f(i)(out)?;
+        }
+        Ok(out)
+    }
+}
+
+/// Writes a bitmap of the fields that are present, then the present fields in order
+///
+/// The bitmap takes one byte per group of 8 fields, and the first field of each group is
+/// its most significant bit, like `bytes::flags8`. Unused bits of the last byte are 0, and
+/// an empty list of fields writes nothing.
+///
+/// ```rust
+/// use cookie_factory::{gen, multi::optional_field_table, bytes::be_u8};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let fields = [Some(be_u8(0x11)), None, Some(be_u8(0x33))];
+///   let (buf, pos) = gen(optional_field_table(fields), &mut buf[..]).unwrap();
+///   assert_eq!(pos, 3);
+///   assert_eq!(buf.len(), 100 - 3);
+/// }
+///
+/// assert_eq!(&buf[..3], &[0b1010_0000u8, 0x11u8, 0x33u8][..]);
+/// ```
+pub fn optional_field_table<S, G, W: Write>(fields: S) -> impl SerializeFn<W>
+where
+    S: AsRef<[Option<G>]>,
+    G: SerializeFn<W>,
+{
+    move |mut out: WriteContext<W>| {
+        let fields = fields.as_ref();
+
+        for group in fields.chunks(8) {
+            let bitmap = group
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| field.is_some())
+                .fold(0u8, |acc, (i, _)| acc | (0x80 >> i));
+            out = crate::bytes::be_u8(bitmap)(out)?;
+        }
+
+        for field in fields.iter().flatten() {
+            out = field(out)?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Writes the number of serializers of an iterator, then applies them with a separator
+/// between each serializer
+///
+/// `count` is called with the number of serializers to get the serializer of the count
+/// prefix, so it can write it with any integer width. Then this works like
+/// `separated_list`. The count covers the elements only: the separators are not counted.
+///
+/// ```rust
+/// use cookie_factory::{gen, multi::prefixed_separated_list, bytes::{be_u16, be_u8}, combinator::string};
+///
+/// let mut buf = [0u8; 100];
+///
+/// let data = vec!["ab", "cd", "ef"];
+/// {
+///   let (buf, pos) = gen(
+///     prefixed_separated_list(|n| be_u8(n as u8), string(","), data.iter().map(string)),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 9);
+///   assert_eq!(buf.len(), 100 - 9);
+/// }
+///
+/// assert_eq!(&buf[..9], &b"\x03ab,cd,ef"[..]);
+///
+/// // an empty list only writes the count
+/// let empty: Vec<&str> = vec![];
+/// let (_, pos) = gen(
+///   prefixed_separated_list(|n| be_u16(n as u16), string(","), empty.iter().map(string)),
+///   &mut buf[..]
+/// ).unwrap();
+/// assert_eq!(pos, 2);
+/// assert_eq!(&buf[..2], &[0, 0][..]);
+/// ```
+pub fn prefixed_separated_list<C, H, F, G, W: Write, It>(
+    count: C,
+    sep: F,
+    values: It,
+) -> impl SerializeFn<W>
+where
+    C: Fn(usize) -> H,
+    H: SerializeFn<W>,
+    F: SerializeFn<W>,
+    G: SerializeFn<W>,
+    It: Clone + Iterator<Item = G>,
+{
+    let list = separated_list(sep, values.clone());
+
+    move |out: WriteContext<W>| count(values.clone().count())(out).and_then(&list)
+}
@@ -149,6 +149,48 @@ pub fn tuple<W: Write, List: Tuple<W>>(l: List) -> impl SerializeFn<W> {
     move |w: WriteContext<W>| l.serialize(w)
 }
 
+/// Applies a comma separated list of serializers in sequence
+///
+/// `compose!(a, b, c)` expands to `pair(a, pair(b, c))`, so unlike `tuple` there is
+/// no limit on the number of serializers.
+///
+/// ```rust
+/// use cookie_factory::{compose, gen, combinator::string, bytes::be_u16};
+///
+/// let mut buf = [0u8; 100];
+///
+/// {
+///   let (buf, pos) = gen(
+///     compose!(
+///       string("abcd"),
+///       be_u16(0x20),
+///       string("efgh"),
+///     ),
+///     &mut buf[..]
+///   ).unwrap();
+///   assert_eq!(pos, 10);
+///   assert_eq!(buf.len(), 100 - 10);
+/// }
+///
+/// assert_eq!(&buf[..10], &b"abcd\x00\x20efgh"[..]);
+/// ```
+///
+/// At least one serializer is required:
+///
+/// ```compile_fail
+/// use cookie_factory::{compose, gen};
+///
+/// let mut buf = [0u8; 100];
+/// gen(compose!(), &mut buf[..]).unwrap();
+/// ```
+#[macro_export]
+macro_rules! compose(
+  ($f:expr $(,)?) => ( $f );
+  ($f:expr, $($rest:expr),+ $(,)?) => (
+    $crate::sequence::pair($f, $crate::compose!($($rest),+))
+  );
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -188,4 +230,34 @@ mod test {
 
         assert_eq!(&buf[..], b"123456780123");
     }
+
+    #[test]
+    fn test_compose() {
+        use crate::bytes::{be_u16, be_u8};
+
+        let mut composed = [0u8; 7];
+        let mut chained = [0u8; 7];
+
+        {
+            let (out, pos) = crate::internal::gen(
+                compose!(be_u8(1), string("abcd"), be_u16(0x0203)),
+                &mut composed[..],
+            )
+            .unwrap();
+            assert_eq!(pos, 7);
+            assert!(out.is_empty());
+        }
+
+        {
+            let serializer = |out| {
+                let out = be_u8(1)(out)?;
+                let out = string("abcd")(out)?;
+                be_u16(0x0203)(out)
+            };
+            gen_simple(serializer, &mut chained[..]).unwrap();
+        }
+
+        assert_eq!(composed, chained);
+        assert_eq!(&composed[..], b"\x01abcd\x02\x03");
+    }
 }
@@ -0,0 +1,171 @@
+//! property tests for the integer serializers
+//!
+//! The values are generated by `quickcheck`, and each serializer goes through
+//! `gen_fuzz_target`, which also checks how it reports running out of space.
+use cookie_factory::bytes::*;
+use cookie_factory::{gen, gen_fuzz_target, GenError};
+use quickcheck::{Arbitrary, QuickCheck};
+
+const ITERATIONS: u64 = 1000;
+
+fn check<A: Arbitrary + std::fmt::Debug>(prop: fn(A) -> bool) {
+    QuickCheck::new().tests(ITERATIONS).quickcheck(prop);
+}
+
+/// Checks `serialize` with `gen_fuzz_target`, then that buffers one byte too short, or any
+/// shorter, report exactly the number of missing bytes
+fn check_serializer<F>(serialize: F, expected: &[u8])
+where
+    F: Fn(&mut [u8]) -> Result<u64, GenError>,
+{
+    gen_fuzz_target(&serialize, expected);
+
+    let len = expected.len();
+    let mut short = [0u8; 16];
+
+    for available in 0..len {
+        match serialize(&mut short[..available]) {
+            Err(GenError::BufferTooSmall(n)) => assert_eq!(n, len - available),
+            Err(e) => panic!("unexpected error: {:?}", e),
+            Ok(_) => panic!("{} bytes should be too short for {} bytes", available, len),
+        }
+    }
+}
+
+// Checks that serializing random values gives the same bytes as `to_*_bytes`, and that
+// `from_*_bytes` gives back the values
+macro_rules! roundtrip_properties(
+  ($to_bytes:ident, $from_bytes:ident; $($name:ident: $serializer:ident($ty:ty)),+ $(,)?) => ($(
+    #[test]
+    fn $name() {
+        fn prop(value: $ty) -> bool {
+            let expected = value.$to_bytes();
+            check_serializer(|out| gen($serializer(value), out).map(|(_, pos)| pos), &expected);
+            <$ty>::$from_bytes(expected) == value
+        }
+
+        check(prop as fn($ty) -> bool);
+    }
+  )+);
+);
+
+roundtrip_properties!(to_be_bytes, from_be_bytes;
+    prop_be_u8: be_u8(u8),
+    prop_be_u16: be_u16(u16),
+    prop_be_u32: be_u32(u32),
+    prop_be_u64: be_u64(u64),
+    prop_be_u128: be_u128(u128),
+    prop_be_i8: be_i8(i8),
+    prop_be_i16: be_i16(i16),
+    prop_be_i32: be_i32(i32),
+    prop_be_i64: be_i64(i64),
+    prop_be_i128: be_i128(i128),
+);
+
+roundtrip_properties!(to_le_bytes, from_le_bytes;
+    prop_le_u8: le_u8(u8),
+    prop_le_u16: le_u16(u16),
+    prop_le_u32: le_u32(u32),
+    prop_le_u64: le_u64(u64),
+    prop_le_u128: le_u128(u128),
+    prop_le_i8: le_i8(i8),
+    prop_le_i16: le_i16(i16),
+    prop_le_i32: le_i32(i32),
+    prop_le_i64: le_i64(i64),
+    prop_le_i128: le_i128(i128),
+);
+
+roundtrip_properties!(to_ne_bytes, from_ne_bytes;
+    prop_ne_u8: ne_u8(u8),
+    prop_ne_u16: ne_u16(u16),
+    prop_ne_u32: ne_u32(u32),
+    prop_ne_u64: ne_u64(u64),
+    prop_ne_i8: ne_i8(i8),
+    prop_ne_i16: ne_i16(i16),
+    prop_ne_i32: ne_i32(i32),
+    prop_ne_i64: ne_i64(i64),
+);
+
+// Same as `roundtrip_properties`, with floats compared through their bits to cover NaNs
+macro_rules! float_roundtrip_properties(
+  ($to_bytes:ident, $from_bytes:ident; $($name:ident: $serializer:ident($ty:ty, $bits:ty)),+ $(,)?) => ($(
+    #[test]
+    fn $name() {
+        fn prop(value: $ty) -> bool {
+            let expected = value.to_bits().$to_bytes();
+            check_serializer(|out| gen($serializer(value), out).map(|(_, pos)| pos), &expected);
+            <$bits>::$from_bytes(expected) == value.to_bits()
+        }
+
+        check(prop as fn($ty) -> bool);
+    }
+  )+);
+);
+
+float_roundtrip_properties!(to_be_bytes, from_be_bytes;
+    prop_be_f32: be_f32(f32, u32),
+    prop_be_f64: be_f64(f64, u64),
+);
+
+float_roundtrip_properties!(to_le_bytes, from_le_bytes;
+    prop_le_f32: le_f32(f32, u32),
+    prop_le_f64: le_f64(f64, u64),
+);
+
+float_roundtrip_properties!(to_ne_bytes, from_ne_bytes;
+    prop_ne_f32: ne_f32(f32, u32),
+    prop_ne_f64: ne_f64(f64, u64),
+);
+
+#[test]
+fn prop_u24() {
+    fn prop(value: u32) -> bool {
+        let value = value & 0x00FF_FFFF;
+
+        let expected = &value.to_be_bytes()[1..];
+        check_serializer(|out| gen(be_u24(value), out).map(|(_, pos)| pos), expected);
+        let be = u32::from_be_bytes([0, expected[0], expected[1], expected[2]]);
+
+        let expected = &value.to_le_bytes()[..3];
+        check_serializer(|out| gen(le_u24(value), out).map(|(_, pos)| pos), expected);
+        let le = u32::from_le_bytes([expected[0], expected[1], expected[2], 0]);
+
+        let expected = if cfg!(target_endian = "big") {
+            &value.to_be_bytes()[1..]
+        } else {
+            &value.to_le_bytes()[..3]
+        };
+        check_serializer(|out| gen(ne_u24(value), out).map(|(_, pos)| pos), expected);
+
+        be == value && le == value
+    }
+
+    check(prop as fn(u32) -> bool);
+}
+
+#[test]
+fn prop_i24() {
+    fn prop(value: i32) -> bool {
+        // sign extended from the 24 lowest bits
+        let value = (value << 8) >> 8;
+
+        let expected = &value.to_be_bytes()[1..];
+        check_serializer(|out| gen(be_i24(value), out).map(|(_, pos)| pos), expected);
+        let be = i32::from_be_bytes([expected[0], expected[1], expected[2], 0]) >> 8;
+
+        let expected = &value.to_le_bytes()[..3];
+        check_serializer(|out| gen(le_i24(value), out).map(|(_, pos)| pos), expected);
+        let le = i32::from_le_bytes([0, expected[0], expected[1], expected[2]]) >> 8;
+
+        let expected = if cfg!(target_endian = "big") {
+            &value.to_be_bytes()[1..]
+        } else {
+            &value.to_le_bytes()[..3]
+        };
+        check_serializer(|out| gen(ne_i24(value), out).map(|(_, pos)| pos), expected);
+
+        be == value && le == value
+    }
+
+    check(prop as fn(i32) -> bool);
+}